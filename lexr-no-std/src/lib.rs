@@ -0,0 +1,52 @@
+//! Lexers defined in a crate without the standard library, where only the prelude of `core` is in scope.
+//!
+//! This crate checks that lexr needs nothing but `alloc` when its `std` feature is off,
+//! by building it for a target that has no standard library at all:
+//! ```text
+//! rustup target add thumbv7m-none-eabi
+//! cargo build -p lexr-no-std --target thumbv7m-none-eabi
+//! ```
+#![no_std]
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use lexr::{lex_rule, Action, LexerBuilder, RuntimeLexer};
+
+/// A token of a firmware command shell.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Cmd(String), Arg(String), Flag(char), Num(u32), Str(String), Help, Eol,
+}
+use Token::*;
+
+lex_rule!{comment -> () {
+    "\n" => |_| break,
+    _ => |_| continue,
+}}
+
+lex_rule!{pub shell -> Token, prev: bool = is_eol {
+    " " => |_| continue,
+    "#" => |_, buf| { comment(buf).deplete(); Eol },
+    "\n" => |_| Eol,
+    "-" / "[a-z]" => |_| continue,
+    r"(?<flag>[a-z])\b" if prev == Some(false) => |_, _, _, caps| Flag(caps["flag"].chars().next().unwrap()),
+    "[0-9]+" => |s| Num(s.parse().unwrap()),
+    "\"" until |_| "\"" => |s| Str(s.to_string()),
+    "[a-z]+" keywords {
+        "help" => Help,
+    } => |s| if prev.is_none_or(|eol| eol) { Cmd(s.to_string()) } else { Arg(s.to_string()) },
+}}
+
+fn is_eol(token: &Token) -> bool {
+    matches!(token, Eol)
+}
+
+/// The arguments of a command, as a lexer built at runtime.
+pub fn args() -> RuntimeLexer<Token> {
+    LexerBuilder::new()
+        .rule(" ", Action::Skip)
+        .rule("[a-z]+", Action::emit(|s, _| Arg(s.to_string())))
+        .rule("[0-9]+", Action::emit(|s, _| Num(s.parse().unwrap())))
+        .build()
+        .unwrap()
+}
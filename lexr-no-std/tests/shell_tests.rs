@@ -0,0 +1,16 @@
+use lexr::SrcLoc;
+use lexr_no_std::{args, shell, Token::*};
+
+#[test]
+fn macro_lexers_need_only_alloc() {
+    let tokens = shell("set -v led 2 # on\nhelp \"set\"").into_vec();
+    assert_eq!(tokens.iter().map(|(token, _)| token).collect::<Vec<_>>(), vec![
+        &Cmd("set".to_string()), &Flag('v'), &Arg("led".to_string()), &Num(2), &Eol, &Help, &Str("set".to_string()),
+    ]);
+    assert_eq!(tokens[6].1, SrcLoc::new((2, 6), (2, 10), (23, 28)));
+}
+
+#[test]
+fn runtime_lexers_need_only_alloc() {
+    assert_eq!(args().lex("led 2").into_token_vec(), vec![Arg("led".to_string()), Num(2)]);
+}
@@ -0,0 +1,280 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+use regex_automata::{
+    Anchored, MatchKind,
+    dfa::{Automaton, StartKind, dense},
+    hybrid,
+    util::{pool::Pool, primitives::StateID, start, syntax},
+};
+
+/// The most states of the product automaton explored before a rule is assumed to be reachable
+const STATE_LIMIT: usize = 100_000;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Information about a rule of a lexer.
+pub struct RuleInfo {
+    /// The position of the rule in the lexer, counting the rules of included rule sets
+    pub index: usize,
+    /// The pattern as written in the rule
+    pub source: String,
+    /// The name given with `#[name(..)]`
+    pub name: Option<&'static str>,
+    /// The priority given with `#[priority(..)]`
+    pub priority: i32,
+    /// The regex that the pattern is compiled to
+    pub regex: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A rule that can never be selected, as every input it matches is also matched by rules tried before it.
+pub struct UnreachableRule {
+    /// The unreachable rule
+    pub rule: RuleInfo,
+    /// The shortest input matched by the rule. This is `None` if the rule can not match anything at all
+    pub witness: Option<String>,
+    /// The rule that is selected instead for the witness
+    pub shadowed_by: Option<RuleInfo>,
+}
+
+/// A rule prepared for analysis
+pub(crate) struct Candidate<'a> {
+    pub regex: &'a str,
+    pub case_insensitive: bool,
+    pub dot_all: bool,
+}
+
+pub(crate) fn build_dfa(candidate: &Candidate) -> Option<dense::DFA<Vec<u32>>> {
+    dense::Builder::new()
+        .configure(dense::Config::new()
+            .start_kind(StartKind::Anchored)
+            .match_kind(MatchKind::All)
+            .unicode_word_boundary(true))
+        .syntax(syntax::Config::new()
+            .case_insensitive(candidate.case_insensitive)
+            .dot_matches_new_line(candidate.dot_all))
+        .build(candidate.regex)
+        .ok()
+}
+
+fn start_state(dfa: &dense::DFA<Vec<u32>>) -> Option<StateID> {
+    dfa.start_state(&start::Config::new().anchored(Anchored::Yes)).ok()
+}
+
+/// The outcome of searching for an input selecting a rule
+pub(crate) enum Search {
+    Reachable,
+    Unreachable,
+    Unknown,
+}
+
+/// Searches for an input where `rule` matches, and none of the `earlier` rules do.
+///
+/// This is a breadth first search through the product of the automata of the rules,
+/// where an earlier rule matching any prefix of the input rules the input out.
+pub(crate) fn search(rule: &dense::DFA<Vec<u32>>, earlier: &[&dense::DFA<Vec<u32>>]) -> Search {
+    let (Some(start), Some(earlier_starts)) = (start_state(rule), earlier.iter().map(|dfa| start_state(dfa)).collect::<Option<Vec<_>>>()) else {
+        return Search::Unknown
+    };
+
+    let bytes = representatives(rule, earlier);
+
+    // Inputs the automaton of the rule gives up on are skipped, but then the rule can not be proven unreachable
+    let mut gave_up = false;
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    let initial = (start, false, earlier_starts);
+    seen.insert(initial.clone());
+    queue.push_back((initial, Vec::<u8>::new()));
+
+    while let Some(((state, matched, earlier_states), input)) = queue.pop_front() {
+        if seen.len() > STATE_LIMIT {
+            return Search::Unknown
+        }
+
+        // Try ending the input here
+        let eoi_blocked = earlier.iter().zip(&earlier_states).any(|(dfa, s)| dfa.is_match_state(dfa.next_eoi_state(*s)));
+        if !eoi_blocked && (matched || rule.is_match_state(rule.next_eoi_state(state))) && core::str::from_utf8(&input).is_ok() {
+            return Search::Reachable
+        }
+
+        for &byte in &bytes {
+            let next_earlier = earlier.iter().zip(&earlier_states).map(|(dfa, s)| dfa.next_state(*s, byte)).collect::<Vec<_>>();
+            if earlier.iter().zip(&next_earlier).any(|(dfa, s)| dfa.is_match_state(*s)) {
+                continue
+            }
+
+            // The state of the rule is irrelevant after it has matched, so it is left at the start state
+            let (next, next_matched) = if matched {
+                (state, true)
+            } else {
+                let next = rule.next_state(state, byte);
+                if rule.is_quit_state(next) {
+                    gave_up = true;
+                    continue
+                }
+                if rule.is_match_state(next) {
+                    (start, true)
+                } else if rule.is_dead_state(next) {
+                    continue
+                } else {
+                    (next, false)
+                }
+            };
+
+            let key = (next, next_matched, next_earlier);
+            if seen.insert(key.clone()) {
+                let mut input = input.clone();
+                input.push(byte);
+                queue.push_back((key, input));
+            }
+        }
+    }
+
+    if gave_up { Search::Unknown } else { Search::Unreachable }
+}
+
+/// The shortest input the rule matches entirely.
+pub(crate) fn shortest_match(rule: &dense::DFA<Vec<u32>>) -> Option<String> {
+    let start = start_state(rule)?;
+    let bytes = representatives(rule, &[]);
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back((start, Vec::new()));
+
+    while let Some((state, input)) = queue.pop_front() {
+        if seen.len() > STATE_LIMIT {
+            return None
+        }
+        if rule.is_match_state(rule.next_eoi_state(state)) {
+            if let Ok(input) = String::from_utf8(input.clone()) {
+                return Some(input)
+            }
+        }
+
+        for &byte in &bytes {
+            let next = rule.next_state(state, byte);
+            if !rule.is_dead_state(next) && !rule.is_quit_state(next) && seen.insert(next) {
+                let mut input = input.clone();
+                input.push(byte);
+                queue.push_back((next, input));
+            }
+        }
+    }
+
+    None
+}
+
+/// The shortest input `rule` matches entirely, which has a shorter prefix that `earlier` matches entirely,
+/// returned as the prefix and the input.
+///
+/// This is a breadth first search through the product of the automata,
+/// where the automaton of `earlier` is dropped once it has matched a prefix, and only the length of the prefix is kept.
+#[cfg(feature = "std")]
+pub(crate) fn longer_match(rule: &dense::DFA<Vec<u32>>, earlier: &dense::DFA<Vec<u32>>) -> Option<(String, String)> {
+    let (start, earlier_start) = (start_state(rule)?, start_state(earlier)?);
+    let bytes = representatives(rule, &[earlier]);
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert((start, Some(earlier_start)));
+    queue.push_back((start, Some(earlier_start), None, Vec::new()));
+
+    while let Some((state, earlier_state, prefix, input)) = queue.pop_front() {
+        if seen.len() > STATE_LIMIT {
+            return None
+        }
+        if let Some(len) = prefix {
+            if input.len() > len && rule.is_match_state(rule.next_eoi_state(state)) {
+                if let (Ok(prefix), Ok(input)) = (core::str::from_utf8(&input[..len]), core::str::from_utf8(&input)) {
+                    return Some((prefix.into(), input.into()))
+                }
+            }
+        }
+
+        let (earlier_state, prefix) = match earlier_state {
+            Some(s) if earlier.is_match_state(earlier.next_eoi_state(s)) => (None, Some(input.len())),
+            s => (s, prefix),
+        };
+        for &byte in &bytes {
+            let next = rule.next_state(state, byte);
+            if rule.is_dead_state(next) || rule.is_quit_state(next) {
+                continue
+            }
+            let next_earlier = match earlier_state {
+                Some(s) => {
+                    let next = earlier.next_state(s, byte);
+                    if earlier.is_dead_state(next) || earlier.is_quit_state(next) {
+                        continue
+                    }
+                    Some(next)
+                }
+                None => None,
+            };
+            if seen.insert((next, next_earlier)) {
+                let mut input = input.clone();
+                input.push(byte);
+                queue.push_back((next, next_earlier, prefix, input));
+            }
+        }
+    }
+
+    None
+}
+
+/// One byte from each class of bytes that all of the automata treat the same.
+///
+/// The smallest byte of each class is picked, so ASCII is preferred.
+fn representatives(rule: &dense::DFA<Vec<u32>>, earlier: &[&dense::DFA<Vec<u32>>]) -> Vec<u8> {
+    let mut seen = BTreeSet::new();
+    (0..=255u8).filter(|&byte| {
+        let classes = core::iter::once(rule).chain(earlier.iter().copied())
+            .map(|dfa| dfa.byte_classes().get(byte))
+            .collect::<Vec<_>>();
+        seen.insert(classes)
+    }).collect()
+}
+
+/// Decides if a rule that does not match the source could match once more source follows it.
+///
+/// The automaton is built lazily, as the states are visited, so patterns with large Unicode classes stay cheap.
+pub(crate) struct Continuation {
+    dfa: hybrid::dfa::DFA,
+    cache: Pool<hybrid::dfa::Cache, Box<dyn Fn() -> hybrid::dfa::Cache + Send + Sync>>,
+}
+
+impl Continuation {
+    pub fn new(candidate: &Candidate) -> Option<Self> {
+        let dfa = hybrid::dfa::DFA::builder()
+            .configure(hybrid::dfa::Config::new().unicode_word_boundary(true))
+            .syntax(syntax::Config::new()
+                .case_insensitive(candidate.case_insensitive)
+                .dot_matches_new_line(candidate.dot_all))
+            .build(candidate.regex)
+            .ok()?;
+        let cache_dfa = dfa.clone();
+        let cache = Pool::new(Box::new(move || cache_dfa.create_cache()) as Box<dyn Fn() -> _ + Send + Sync>);
+        Some(Self { dfa, cache })
+    }
+
+    /// Whether `src` may be the beginning of an input the rule matches.
+    ///
+    /// This is assumed whenever the automaton gives up, like on non-ASCII text next to a Unicode word boundary.
+    pub fn is_prefix(&self, src: &str) -> bool {
+        let mut cache = self.cache.get();
+        let Ok(mut state) = self.dfa.start_state(&mut cache, &start::Config::new().anchored(Anchored::Yes)) else {
+            return true
+        };
+        for &byte in src.as_bytes() {
+            match self.dfa.next_state(&mut cache, state, byte) {
+                Ok(next) if next.is_dead() => return false,
+                Ok(next) if !next.is_quit() => state = next,
+                _ => return true,
+            }
+        }
+        true
+    }
+}
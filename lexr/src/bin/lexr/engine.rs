@@ -0,0 +1,79 @@
+use lexr::{LexError, LexerBuilder, RuntimeLexer, SrcLoc};
+use crate::spec::{Action, Pattern, Spec, SpecError};
+
+/// A lexer compiled from a specification at runtime, with the same semantics as a `lex_rule!`.
+pub struct Engine {
+    lexer: RuntimeLexer<usize>,
+    /// The token names, which the lexer produces the indices of
+    names: Vec<String>,
+}
+
+/// A token produced by an [`Engine`].
+pub struct Token<'a> {
+    pub name: &'a str,
+    pub lexeme: &'a str,
+    pub loc: SrcLoc,
+}
+
+impl Engine {
+    pub fn new(spec: Spec) -> Result<Self, SpecError> {
+        let mut builder = LexerBuilder::new();
+        if spec.case_insensitive {
+            builder = builder.case_insensitive();
+        }
+        if spec.ascii_classes {
+            builder = builder.ascii_classes();
+        }
+        if spec.dot_all {
+            builder = builder.dot_all();
+        }
+
+        let mut names = Vec::new();
+        // The lines of the rules of each mode, to report a rule that does not build by its line
+        let mut lines: Vec<(String, Vec<usize>)> = Vec::new();
+        for rule in spec.rules {
+            match lines.iter_mut().find(|(mode, _)| *mode == rule.mode) {
+                Some((_, mode_lines)) => mode_lines.push(rule.line),
+                None => lines.push((rule.mode.clone(), vec![rule.line])),
+            }
+
+            let action = match rule.action {
+                Action::Token(name) => {
+                    let index = names.len();
+                    names.push(name);
+                    lexr::Action::emit(move |_, _| index)
+                }
+                Action::Skip => lexr::Action::Skip,
+                Action::Break => lexr::Action::Break,
+                Action::Push(mode) => lexr::Action::push(mode),
+                Action::Pop => lexr::Action::Pop,
+                Action::Switch(mode) => lexr::Action::switch(mode),
+            };
+            builder = builder.mode(&rule.mode);
+            builder = match rule.pattern {
+                Pattern::Builtin(name) => builder.builtin(&name, action),
+                Pattern::Regex(regex) => builder.rule(regex, action),
+            };
+            if rule.bol {
+                builder = builder.bol();
+            }
+            if let Some(trail) = rule.trail {
+                builder = builder.trail(trail);
+            }
+        }
+
+        let lexer = builder.build().map_err(|err| SpecError {
+            line: lines.iter().find(|(mode, _)| *mode == err.mode).map_or(0, |(_, mode_lines)| mode_lines[err.rule]),
+            message: err.message,
+        })?;
+        Ok(Self { lexer, names })
+    }
+
+    /// Tokenizes `src`, passing each token to `emit` as it is produced.
+    pub fn tokenize<'a>(&'a self, src: &'a str, mut emit: impl FnMut(Token<'a>)) -> Result<(), LexError> {
+        self.lexer.try_lex(src, |name, loc| {
+            let (start, end) = loc.get_abs_loc();
+            emit(Token { name: &self.names[name], lexeme: &src[start..end], loc })
+        })
+    }
+}
@@ -0,0 +1,117 @@
+use lexr::{import_flex, import_ocamllex};
+use super::{IO_ERROR, SUCCESS, USAGE, USAGE_ERROR};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Dialect {
+    Flex,
+    Ocamllex,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    Spec,
+    Rust,
+}
+
+struct Args {
+    from: Dialect,
+    to: Target,
+    name: String,
+    file: String,
+}
+
+/// Runs `lexr import`, which prints a flex or ocamllex specification as a lexr specification or `lex_rule!` source.
+pub fn run(args: Vec<String>) -> i32 {
+    let Args { from, to, name, file } = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return SUCCESS
+        }
+        Err(err) => {
+            eprintln!("lexr: {}\n\n{}", err, USAGE);
+            return USAGE_ERROR
+        }
+    };
+
+    let src = match std::fs::read_to_string(&file) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("lexr: {}: {}", file, err);
+            return IO_ERROR
+        }
+    };
+    let imported = match from {
+        Dialect::Flex => import_flex(&src),
+        Dialect::Ocamllex => import_ocamllex(&src),
+    };
+    if let Ok(lexer) = &imported {
+        for warning in &lexer.warnings {
+            eprintln!("{}:{}: warning: {}", file, warning.line, warning.message);
+        }
+    }
+    let output = imported.and_then(|lexer| match to {
+        Target::Spec => Ok(lexer.to_spec()),
+        Target::Rust => lexer.to_lex_rule(&name),
+    });
+
+    match output {
+        Ok(output) => {
+            print!("{}", output);
+            SUCCESS
+        }
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{}:{}: {}", file, err.line, err.message);
+            }
+            USAGE_ERROR
+        }
+    }
+}
+
+/// Parses the arguments after `import`, or returns `None` if help was requested.
+fn parse_args(args: Vec<String>) -> Result<Option<Args>, String> {
+    let mut from = None;
+    let mut to = Target::Spec;
+    let mut name = "lex".to_string();
+    let mut file = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--from" => {
+                from = match args.next().as_deref() {
+                    Some("flex") => Some(Dialect::Flex),
+                    Some("ocamllex") => Some(Dialect::Ocamllex),
+                    Some(other) => return Err(format!("Unknown dialect '{}'", other)),
+                    None => return Err(format!("Expected a dialect after {}", arg)),
+                }
+            }
+            "--to" => {
+                to = match args.next().as_deref() {
+                    Some("spec") => Target::Spec,
+                    Some("rust") => Target::Rust,
+                    Some(other) => return Err(format!("Unknown output '{}'", other)),
+                    None => return Err(format!("Expected an output after {}", arg)),
+                }
+            }
+            "--name" => name = args.next().ok_or(format!("Expected a name after {}", arg))?,
+            option if option.starts_with('-') => return Err(format!("Unknown option '{}'", option)),
+            _ if file.is_some() => return Err(format!("Unexpected argument '{}'", arg)),
+            _ => file = Some(arg),
+        }
+    }
+
+    let file = file.ok_or("Expected a file to import")?;
+    let from = match from {
+        Some(from) => from,
+        None => match file.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("l" | "lex" | "ll") => Dialect::Flex,
+            Some("mll") => Dialect::Ocamllex,
+            _ => return Err(format!("Could not tell the dialect of {} from its extension, use --from", file)),
+        },
+    };
+
+    Ok(Some(Args { from, to, name, file }))
+}
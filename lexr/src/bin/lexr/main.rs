@@ -0,0 +1,170 @@
+//! The `lexr` command line tool, which tokenizes input with a lexer specification loaded at runtime.
+
+mod engine;
+mod import;
+mod output;
+mod spec;
+
+use std::io::{self, Read, Write};
+use engine::Engine;
+use output::{Format, Printer};
+use spec::Spec;
+
+const USAGE: &str = "\
+Usage: lexr [OPTIONS] SPEC [INPUT]...
+       lexr import [IMPORT OPTIONS] FILE
+
+Tokenizes each INPUT with the lexer specification in SPEC.
+Reads standard input when no INPUT is given, or when INPUT is `-`.
+
+`lexr import` prints the flex or ocamllex specification in FILE as a lexr specification or as Rust source.
+
+Options:
+  -f, --format <FORMAT>  How to print the tokens: table, json or color [default: table]
+  -h, --help             Print this help
+
+Import options:
+  --from <DIALECT>       The dialect of FILE: flex or ocamllex [default: from the extension]
+  --to <OUTPUT>          What to print: spec or rust [default: spec]
+  --name <NAME>          The name of the lexer in the Rust source [default: lex]
+
+Exit codes:
+  0  Every input was tokenized
+  1  An input contained text that no rule matched
+  2  The arguments or the specification were invalid, or could not be imported
+  3  An input could not be read, or the output could not be written";
+
+/// Every input was tokenized
+const SUCCESS: i32 = 0;
+/// An input contained text that no rule matched
+const LEX_ERROR: i32 = 1;
+/// The arguments or the specification were invalid, or could not be imported
+const USAGE_ERROR: i32 = 2;
+/// An input could not be read, or the output could not be written
+const IO_ERROR: i32 = 3;
+
+struct Args {
+    spec: String,
+    format: Format,
+    inputs: Vec<String>,
+}
+
+fn main() {
+    std::process::exit(run(std::env::args().skip(1).collect()))
+}
+
+fn run(args: Vec<String>) -> i32 {
+    if args.first().is_some_and(|arg| arg == "import") {
+        return import::run(args[1..].to_vec())
+    }
+
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return SUCCESS
+        }
+        Err(err) => {
+            eprintln!("lexr: {}\n\n{}", err, USAGE);
+            return USAGE_ERROR
+        }
+    };
+
+    let text = match std::fs::read_to_string(&args.spec) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("lexr: {}: {}", args.spec, err);
+            return IO_ERROR
+        }
+    };
+    let engine = match Spec::parse(&text).and_then(Engine::new) {
+        Ok(engine) => engine,
+        Err(err) => {
+            eprintln!("lexr: {}: {}", args.spec, err);
+            return USAGE_ERROR
+        }
+    };
+
+    let mut code = SUCCESS;
+    let many = args.inputs.len() > 1;
+    for (i, input) in args.inputs.iter().enumerate() {
+        let (name, src) = match read_input(input) {
+            Ok(src) if input == "-" => ("<stdin>", src),
+            Ok(src) => (input.as_str(), src),
+            Err(err) => {
+                eprintln!("lexr: {}: {}", input, err);
+                code = IO_ERROR;
+                continue
+            }
+        };
+
+        let mut stdout = io::stdout().lock();
+        if many && args.format == Format::Table {
+            let heading = writeln!(stdout, "{}==> {} <==", if i > 0 { "\n" } else { "" }, name);
+            if heading.is_err() {
+                return IO_ERROR
+            }
+        }
+
+        let mut printer = Printer::new(stdout, args.format, name, &src);
+        let mut written = Ok(());
+        let lexed = engine.tokenize(&src, |token| if written.is_ok() {
+            written = printer.token(&token);
+        });
+
+        let end = lexed.as_ref().map_or_else(|err| err.loc().get_abs_loc().0, |_| src.len());
+        if written.and_then(|_| printer.finish(end)).is_err() {
+            return IO_ERROR
+        }
+        if let Err(err) = lexed {
+            eprintln!("lexr: {}: {}", name, err);
+            code = code.max(LEX_ERROR);
+        }
+    }
+
+    code
+}
+
+/// Parses the arguments, or returns `None` if help was requested.
+fn parse_args(args: Vec<String>) -> Result<Option<Args>, String> {
+    let mut format = Format::Table;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--format" => {
+                format = match args.next().as_deref() {
+                    Some("table") => Format::Table,
+                    Some("json") => Format::Json,
+                    Some("color") => Format::Color,
+                    Some(other) => return Err(format!("Unknown format '{}'", other)),
+                    None => return Err(format!("Expected a format after {}", arg)),
+                }
+            }
+            "-" => positional.push(arg),
+            option if option.starts_with('-') => return Err(format!("Unknown option '{}'", option)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let spec = positional.next().ok_or("Expected a specification file")?;
+    let mut inputs = positional.collect::<Vec<_>>();
+    if inputs.is_empty() {
+        inputs.push("-".to_string());
+    }
+
+    Ok(Some(Args { spec, format, inputs }))
+}
+
+fn read_input(input: &str) -> io::Result<String> {
+    if input == "-" {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src)?;
+        Ok(src)
+    } else {
+        std::fs::read_to_string(input)
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+use lexr::{AnsiTheme, Highlighted, SrcLoc};
+use crate::engine::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// One row for each token, with its location, name and lexeme
+    Table,
+    /// A line of JSON for each token
+    Json,
+    /// The source, with each token colored by its name
+    Color,
+}
+
+/// The colors tokens are printed in, picked by the name of the token
+const COLORS: [u8; 12] = [31, 32, 33, 34, 35, 36, 91, 92, 93, 94, 95, 96];
+
+/// Prints the tokens of an input in a [`Format`].
+pub struct Printer<'a, W: Write> {
+    out: W,
+    format: Format,
+    file: &'a str,
+    src: &'a str,
+    /// The tokens of the input in color mode, which is highlighted when it is finished
+    tokens: Vec<(String, SrcLoc)>,
+    /// The style of each token name seen in color mode
+    theme: AnsiTheme,
+}
+
+impl<'a, W: Write> Printer<'a, W> {
+    pub fn new(out: W, format: Format, file: &'a str, src: &'a str) -> Self {
+        Self { out, format, file, src, tokens: Vec::new(), theme: AnsiTheme::new() }
+    }
+
+    pub fn token(&mut self, token: &Token) -> io::Result<()> {
+        match self.format {
+            Format::Table => writeln!(self.out, "{:<12} {:<16} {:?}", token.loc.to_string(), token.name, token.lexeme),
+            Format::Json => {
+                let (start, end) = token.loc.get_abs_loc();
+                let (start_line, start_col, end_line, end_col) = token.loc.get_loc();
+                writeln!(self.out,
+                    r#"{{"file":{},"token":{},"lexeme":{},"loc":{{"abs_range":[{},{}],"start":[{},{}],"end":[{},{}]}}}}"#,
+                    json_string(self.file), json_string(token.name), json_string(token.lexeme),
+                    start, end, start_line, start_col, end_line, end_col)
+            }
+            Format::Color => {
+                let color = COLORS[token.name.bytes().fold(0usize, |hash, b| hash.wrapping_mul(31).wrapping_add(b as usize)) % COLORS.len()];
+                self.theme = std::mem::take(&mut self.theme).style(token.name, &color.to_string());
+                self.tokens.push((token.name.to_string(), token.loc));
+                Ok(())
+            }
+        }
+    }
+
+    /// Finishes the input. In color mode, the input is printed up to `end`, with its tokens highlighted.
+    pub fn finish(mut self, end: usize) -> io::Result<()> {
+        if self.format == Format::Color {
+            let highlighted = Highlighted::new(&self.src[..end], self.tokens, |name| Some(name.clone()));
+            write!(self.out, "{}", highlighted.ansi(&self.theme))?;
+        }
+        self.out.flush()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    lexr::textmate::write_json_string(&mut json, text);
+    json
+}
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+use lexr::{patterns, LexerBuilder};
+
+/// A lexer specification, holding the same rules as a `lex_rule!`.
+///
+/// Each line is a rule, a `flags` line, a `mode` line or a comment starting with `//`:
+/// ```text
+/// flags case_insensitive
+/// ws => skip
+/// r"[a-z]+" => Id
+/// ^"#" => Directive
+/// "[0-9]+" / r"\.\." => Int
+/// "\"" => push string
+/// eof => Eof
+///
+/// mode string
+/// r#"[^"]+"# => Str
+/// "\"" => pop
+/// ```
+/// Patterns are string literals or builtin patterns, optionally preceded by `^` and followed by `/` and a trailing context.
+/// The action is `skip`, `break`, `push MODE`, `pop`, `switch MODE` or the name of the token to produce.
+/// Rules are added to the mode of the last `mode` line, or the initial mode before the first.
+pub struct Spec {
+    pub case_insensitive: bool,
+    pub ascii_classes: bool,
+    pub dot_all: bool,
+    pub rules: Vec<Rule>,
+}
+
+pub struct Rule {
+    /// The line of the rule in the specification
+    pub line: usize,
+    pub mode: String,
+    pub pattern: Pattern,
+    pub trail: Option<String>,
+    pub bol: bool,
+    pub action: Action,
+}
+
+pub enum Pattern {
+    /// A builtin pattern, by its name
+    Builtin(String),
+    Regex(String),
+}
+
+pub enum Action {
+    Token(String),
+    Skip,
+    Break,
+    Push(String),
+    Pop,
+    Switch(String),
+}
+
+#[derive(Debug)]
+/// An invalid line of a specification.
+pub struct SpecError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Spec {
+    pub fn parse(text: &str) -> Result<Self, SpecError> {
+        let mut spec = Spec { case_insensitive: false, ascii_classes: false, dot_all: false, rules: Vec::new() };
+        let mut mode = LexerBuilder::<()>::INITIAL.to_string();
+
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| SpecError { line: i + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue
+            }
+
+            if let Some(name) = line.strip_prefix("mode ") {
+                mode = name.trim().to_string();
+                continue
+            }
+
+            if let Some(flags) = line.strip_prefix("flags ") {
+                for flag in flags.split_whitespace() {
+                    match flag {
+                        "case_insensitive" => spec.case_insensitive = true,
+                        "ascii_classes" => spec.ascii_classes = true,
+                        "dot_all" => spec.dot_all = true,
+                        _ => return Err(error(format!("Unknown flag '{}'", flag))),
+                    }
+                }
+                continue
+            }
+
+            spec.rules.push(Self::parse_rule(i + 1, &mode, line).map_err(error)?);
+        }
+
+        Ok(spec)
+    }
+
+    fn parse_rule(line_number: usize, mode: &str, line: &str) -> Result<Rule, String> {
+        let (bol, line) = match line.strip_prefix('^') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, line),
+        };
+
+        let (pattern, rest) = if line.starts_with('"') || line.starts_with("r\"") || line.starts_with("r#") {
+            let (regex, rest) = literal(line)?;
+            (Pattern::Regex(regex), rest)
+        } else {
+            let end = line.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(line.len());
+            match &line[..end] {
+                "" => return Err("Expected a pattern".to_string()),
+                name if patterns::builtin(name).is_none() => return Err(format!("Unknown builtin pattern '{}'", name)),
+                name => (Pattern::Builtin(name.to_string()), &line[end..]),
+            }
+        };
+        let (trail, rest) = match rest.trim_start().strip_prefix('/') {
+            Some(trail) => {
+                let (trail, rest) = literal(trail.trim_start())?;
+                (Some(trail), rest)
+            }
+            None => (None, rest),
+        };
+        let source = &line[..line.len() - rest.len()];
+
+        let Some(rest) = rest.trim_start().strip_prefix("=>") else {
+            return Err(format!("Expected `=>` after {}", source))
+        };
+        let action = match rest.split("//").next().unwrap().trim() {
+            "skip" => Action::Skip,
+            "break" => Action::Break,
+            "pop" => Action::Pop,
+            action if action.starts_with("push ") => Action::Push(action["push ".len()..].trim().to_string()),
+            action if action.starts_with("switch ") => Action::Switch(action["switch ".len()..].trim().to_string()),
+            "" => return Err(format!("Expected an action after {} =>", source)),
+            name if name.chars().all(|c| c.is_alphanumeric() || c == '_') => Action::Token(name.to_string()),
+            other => return Err(format!("Invalid token name '{}'", other)),
+        };
+
+        Ok(Rule { line: line_number, mode: mode.to_string(), pattern, trail, bol, action })
+    }
+}
+
+/// Parses a string literal at the start of `text`, with the escapes of Rust string literals.
+///
+/// Returns the value of the literal and the text after it.
+fn literal(text: &str) -> Result<(String, &str), String> {
+    if !text.starts_with('"') && !text.starts_with('r') {
+        return Err("Expected a string literal".to_string())
+    }
+    if let Some(raw) = text.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw[hashes..].strip_prefix('"').ok_or("Expected `\"` to start the raw string")?;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let end = body.find(&terminator).ok_or("Unterminated raw string")?;
+        return Ok((body[..end].to_string(), &body[end + terminator.len()..]))
+    }
+
+    let mut value = String::new();
+    let mut chars = text[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &text[i + 2..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some(c @ ('\\' | '"' | '\'')) => c,
+                    Some('x') => {
+                        let code = chars.by_ref().take(2).map(|(_, c)| c).collect::<String>();
+                        u8::from_str_radix(&code, 16).ok().filter(u8::is_ascii).map(char::from)
+                            .ok_or(format!("Invalid escape \\x{}", code))?
+                    }
+                    Some('u') => {
+                        let code = chars.by_ref().map(|(_, c)| c).take_while(|c| *c != '}').collect::<String>();
+                        code.strip_prefix('{')
+                            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                            .and_then(char::from_u32)
+                            .ok_or(format!("Invalid unicode escape \\u{}}}", code))?
+                    }
+                    Some(other) => return Err(format!("Unknown escape \\{}", other)),
+                    None => break,
+                };
+                value.push(escaped);
+            }
+            c => value.push(c),
+        }
+    }
+
+    Err("Unterminated string".to_string())
+}
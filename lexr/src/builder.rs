@@ -0,0 +1,402 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use crate::{patterns, LexBuf, LexError, Lexer, SrcLoc};
+use crate::rule_table::{Buffered, Pattern, Placement, Resume, Resumed, RuleSpec, RuleTable};
+use crate::stats::StatsRecorder;
+#[cfg(feature = "stats")]
+use crate::stats::{RuleStats, Stats};
+#[cfg(feature = "std")]
+use crate::textmate::{ExportMode, TextMate, TextMateGrammar, Transition};
+
+/// Produces a token from a lexeme and its location.
+pub type EmitFn<T> = Box<dyn Fn(&str, SrcLoc) -> T>;
+
+/// What a rule of a [`LexerBuilder`] does when it is selected.
+pub enum Action<T> {
+    /// Produces a token from the lexeme and its location
+    Emit(EmitFn<T>),
+    /// Skips the lexeme, like `continue` in an action of [`lex_rule!`](crate::lex_rule!)
+    Skip,
+    /// Stops lexing, like `break` in an action of [`lex_rule!`](crate::lex_rule!)
+    Break,
+    /// Skips the lexeme, and switches to the rules of a mode until it is popped
+    Push(String),
+    /// Skips the lexeme, and returns to the mode that was active before the last push.
+    /// Popping the initial mode does nothing
+    Pop,
+    /// Skips the lexeme, and replaces the current mode with another
+    Switch(String),
+}
+
+impl<T> Action<T> {
+    /// Produces a token from the lexeme and its location.
+    pub fn emit(action: impl Fn(&str, SrcLoc) -> T + 'static) -> Self {
+        Action::Emit(Box::new(action))
+    }
+
+    /// Produces a clone of `token`.
+    pub fn token(token: T) -> Self where T: Clone + 'static {
+        Action::Emit(Box::new(move |_, _| token.clone()))
+    }
+
+    /// Switches to the rules of `mode`.
+    pub fn push(mode: impl Into<String>) -> Self {
+        Action::Push(mode.into())
+    }
+
+    /// Replaces the current mode with `mode`.
+    pub fn switch(mode: impl Into<String>) -> Self {
+        Action::Switch(mode.into())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A rule that could not be compiled by [`LexerBuilder::build`].
+pub struct BuildError {
+    /// The mode the rule was added to
+    pub mode: String,
+    /// The index of the rule among the rules of its mode, in the order they were added
+    pub rule: usize,
+    pub message: String,
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+struct BuilderRule<T> {
+    source: String,
+    pattern: Result<Pattern, String>,
+    trail: Option<String>,
+    bol: bool,
+    action: Action<T>,
+}
+
+/// Defines a lexer at runtime, for rules that are not known when compiling, like rules read from a file.
+///
+/// Rules are added with a pattern and an [`Action`], and are tried in the order they were added,
+/// with the same first match semantics as [`lex_rule!`](crate::lex_rule!).
+///
+/// Rules can be grouped in modes, where only the rules of the current mode are tried.
+/// Rules are added to the [`INITIAL`](Self::INITIAL) mode, until another mode is selected with [`mode`](Self::mode).
+pub struct LexerBuilder<T> {
+    modes: Vec<(String, Vec<BuilderRule<T>>)>,
+    current: usize,
+    case_insensitive: bool,
+    ascii_classes: bool,
+    dot_all: bool,
+}
+
+impl<T> Default for LexerBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> LexerBuilder<T> {
+    /// The mode the lexer starts in.
+    pub const INITIAL: &'static str = "INITIAL";
+
+    pub fn new() -> Self {
+        Self {
+            modes: vec![(Self::INITIAL.to_string(), Vec::new())],
+            current: 0,
+            case_insensitive: false,
+            ascii_classes: false,
+            dot_all: false,
+        }
+    }
+
+    /// Adds a rule with a regex pattern.
+    pub fn rule(self, pattern: impl Into<String>, action: Action<T>) -> Self {
+        let pattern = pattern.into();
+        let source = format!("{:?}", pattern);
+        self.add(BuilderRule { source, pattern: Ok(Pattern::Regex(pattern)), trail: None, bol: false, action })
+    }
+
+    /// Adds a rule with a [builtin pattern](crate::patterns), by the name it is written as in a `lex_rule!`, like `ws` or `eof`.
+    pub fn builtin(self, name: &str, action: Action<T>) -> Self {
+        let pattern = patterns::builtin(name).map(Pattern::Builtin).ok_or_else(|| format!("Unknown builtin pattern '{}'", name));
+        self.add(BuilderRule { source: name.to_string(), pattern, trail: None, bol: false, action })
+    }
+
+    /// Only allows the last added rule to match at the beginning of a line, like `^` in a `lex_rule!`.
+    pub fn bol(mut self) -> Self {
+        if let Some(rule) = self.modes[self.current].1.last_mut() {
+            rule.bol = true;
+        }
+        self
+    }
+
+    /// Only lets the last added rule match if `pattern` matches after it, like `/` in a `lex_rule!`.
+    ///
+    /// The trailing context is not consumed, and is not part of the lexeme.
+    pub fn trail(mut self, pattern: impl Into<String>) -> Self {
+        if let Some(rule) = self.modes[self.current].1.last_mut() {
+            let pattern = pattern.into();
+            rule.source = format!("{} / {:?}", rule.source, pattern);
+            rule.trail = Some(pattern);
+        }
+        self
+    }
+
+    /// Adds the following rules to `mode`, which is created if it does not exist.
+    pub fn mode(mut self, mode: &str) -> Self {
+        self.current = match self.modes.iter().position(|(name, _)| name == mode) {
+            Some(i) => i,
+            None => {
+                self.modes.push((mode.to_string(), Vec::new()));
+                self.modes.len() - 1
+            }
+        };
+        self
+    }
+
+    /// Matches the patterns of all rules case insensitively.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    /// Only lets the classes of all patterns match ASCII.
+    pub fn ascii_classes(mut self) -> Self {
+        self.ascii_classes = true;
+        self
+    }
+
+    /// Lets `.` match newlines in all patterns.
+    pub fn dot_all(mut self) -> Self {
+        self.dot_all = true;
+        self
+    }
+
+    fn add(mut self, rule: BuilderRule<T>) -> Self {
+        self.modes[self.current].1.push(rule);
+        self
+    }
+
+    /// Compiles the rules, which can then be used to lex any number of inputs.
+    pub fn build(self) -> Result<RuntimeLexer<T>, BuildError> {
+        let names = self.modes.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+
+        let modes = self.modes.into_iter().map(|(mode, rules)| {
+            let error = |rule: usize, message: String| BuildError { mode: mode.clone(), rule, message };
+            let mut specs = Vec::with_capacity(rules.len());
+            let mut steps = Vec::with_capacity(rules.len());
+            for (i, rule) in rules.into_iter().enumerate() {
+                steps.push(match rule.action {
+                    Action::Emit(emit) => Step::Emit(emit),
+                    Action::Skip => Step::Skip,
+                    Action::Break => Step::Break,
+                    Action::Push(target) | Action::Switch(target) if !names.contains(&target) => {
+                        return Err(error(i, format!("No mode named '{}' to switch to from {} in mode {}", target, rule.source, mode)))
+                    }
+                    Action::Push(target) => Step::Push(names.iter().position(|name| *name == target).unwrap()),
+                    Action::Switch(target) => Step::Switch(names.iter().position(|name| *name == target).unwrap()),
+                    Action::Pop => Step::Pop,
+                });
+                specs.push(RuleSpec {
+                    pattern: rule.pattern.map_err(|message| error(i, message))?,
+                    source: rule.source.into(),
+                    name: None,
+                    placement: Placement::Declared,
+                    trail: rule.trail,
+                    priority: 0,
+                    bol: rule.bol,
+                    case_insensitive: self.case_insensitive,
+                    ascii_classes: self.ascii_classes,
+                    dot_all: self.dot_all,
+                    guarded: false,
+                    captures: false,
+                    delimited: false,
+                    keywords: &[],
+                });
+            }
+
+            let table = RuleTable::try_new(specs).map_err(|(i, message)| error(i, message))?;
+            Ok(CompiledMode { name: mode, table, steps })
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RuntimeLexer { modes })
+    }
+}
+
+/// An action, with the mode to push resolved
+enum Step<T> {
+    Emit(EmitFn<T>),
+    Skip,
+    Break,
+    Push(usize),
+    Pop,
+    Switch(usize),
+}
+
+struct CompiledMode<T> {
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    name: String,
+    table: RuleTable,
+    steps: Vec<Step<T>>,
+}
+
+/// The compiled rules of a [`LexerBuilder`].
+pub struct RuntimeLexer<T> {
+    modes: Vec<CompiledMode<T>>,
+}
+
+impl<T> RuntimeLexer<T> {
+    /// Creates a lexer for the input, which panics with a [`LexError`] if no rule matches, like the lexers of [`lex_rule!`](crate::lex_rule!).
+    pub fn lex<'r, 'a>(&'r self, buf: impl Into<LexBuf<'a>>) -> Lexer<T, RuntimeTokens<'r, 'a, T>> {
+        Lexer::new(self.tokens(buf.into()))
+    }
+
+    /// Lexes the input, passing each token to `emit` as it is produced.
+    ///
+    /// Stops at the first `break`, and returns an error if no rule matches.
+    pub fn try_lex<'a>(&self, buf: impl Into<LexBuf<'a>>, mut emit: impl FnMut(T, SrcLoc)) -> Result<(), LexError> {
+        let mut tokens = self.tokens(buf.into());
+        while let Some((token, loc)) = tokens.lex_next()? {
+            emit(token, loc)
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    /// Exports the rules as a TextMate grammar, highlighted with the scopes of `textmate`.
+    ///
+    /// Pushed modes become regions, which end at the rules that pop them.
+    pub fn textmate(&self, textmate: &TextMate) -> TextMateGrammar {
+        let modes = self.modes.iter().map(|mode| ExportMode {
+            name: &mode.name,
+            table: &mode.table,
+            transitions: mode.steps.iter().map(|step| match step {
+                Step::Emit(_) | Step::Skip => Transition::None,
+                Step::Break => Transition::Break,
+                Step::Push(mode) => Transition::Push(*mode),
+                Step::Pop => Transition::Pop,
+                Step::Switch(mode) => Transition::Switch(*mode),
+            }).collect(),
+        }).collect::<Vec<_>>();
+        textmate.export(&modes)
+    }
+
+    fn tokens<'a>(&self, buf: LexBuf<'a>) -> RuntimeTokens<'_, 'a, T> {
+        RuntimeTokens {
+            lexer: self,
+            buf,
+            modes: vec![0],
+            stats: self.modes.iter().map(|_| StatsRecorder::default()).collect(),
+        }
+    }
+}
+
+#[doc(hidden)]
+/// The tokens of an input lexed by a [`RuntimeLexer`].
+pub struct RuntimeTokens<'r, 'a, T> {
+    lexer: &'r RuntimeLexer<T>,
+    buf: LexBuf<'a>,
+    /// The stack of active modes. The last is the current mode
+    modes: Vec<usize>,
+    stats: Vec<StatsRecorder>,
+}
+
+impl<T> RuntimeTokens<'_, '_, T> {
+    fn lex_next(&mut self) -> Result<Option<(T, SrcLoc)>, LexError> {
+        loop {
+            // Like the generated lexers, the empty remainder is tried once, so eof can match
+            if self.buf.exhausted() {
+                return Ok(None)
+            }
+            let src: &str = *self.buf.source.borrow();
+
+            let mode = *self.modes.last().unwrap();
+            let compiled = &self.lexer.modes[mode];
+            let Some(found) = compiled.table.select(&self.buf, &self.stats[mode], |_| true) else {
+                return match src.chars().next() {
+                    Some(c) if !self.buf.is_starved() => Err(LexError::UnexpectedChar { c, loc: self.buf.current_loc() }),
+                    _ => Ok(None),
+                }
+            };
+
+            let loc = self.buf.consume(found.lexeme.len());
+            self.stats[mode].selected(found.rule, loc.get_abs_loc());
+            match &compiled.steps[found.rule] {
+                Step::Emit(emit) => return Ok(Some((emit(found.lexeme, loc), loc))),
+                Step::Skip => continue,
+                Step::Break => return Ok(None),
+                Step::Push(mode) => self.modes.push(*mode),
+                Step::Pop => {
+                    if self.modes.len() > 1 {
+                        self.modes.pop();
+                    }
+                }
+                Step::Switch(mode) => *self.modes.last_mut().unwrap() = *mode,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl<'r, 'a, T> Lexer<T, RuntimeTokens<'r, 'a, T>> {
+    /// The statistics of each rule so far, with the modes in the order they were added, and their rules in the order they are tried.
+    ///
+    /// Requires the `stats` feature.
+    pub fn stats(&self) -> Stats {
+        let tokens = &self.iter;
+        Stats {
+            rules: tokens.lexer.modes.iter().zip(&tokens.stats).flat_map(|(mode, recorder)| {
+                mode.table.rules().into_iter().map(|rule| {
+                    let counters = recorder.counters(rule.index);
+                    RuleStats { rule, counters }
+                })
+            }).collect(),
+        }
+    }
+}
+
+impl<'a, T> Buffered<'a> for RuntimeTokens<'_, 'a, T> {
+    fn buf(&self) -> &LexBuf<'a> {
+        &self.buf
+    }
+}
+
+impl<T> Resume for RuntimeTokens<'_, '_, T> {
+    type Token = T;
+
+    fn resume(&mut self, buf: LexBuf<'_>, lex: &mut dyn FnMut(&mut Resumed<'_, T>)) -> SrcLoc {
+        let mut tokens = RuntimeTokens {
+            lexer: self.lexer,
+            buf,
+            modes: core::mem::take(&mut self.modes),
+            stats: core::mem::take(&mut self.stats),
+        };
+        lex(&mut tokens);
+        self.modes = tokens.modes;
+        self.stats = tokens.stats;
+        // The buffer only starves before a step changes anything
+        tokens.buf.current_loc()
+    }
+
+    fn keeps_state(&self) -> bool {
+        self.lexer.modes.len() > 1
+    }
+}
+
+impl<T> Iterator for RuntimeTokens<'_, '_, T> {
+    type Item = (T, SrcLoc);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lex_next().unwrap_or_else(|err| {
+            self.buf.fail(err);
+            None
+        })
+    }
+}
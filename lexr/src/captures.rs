@@ -0,0 +1,116 @@
+use core::ops::Range;
+use regex_automata::util::captures;
+use regex_automata::PatternID;
+use crate::SrcLoc;
+
+/// The capture groups of a matched pattern.
+///
+/// Groups can be accessed by index or by name, along with their location in the source.
+/// Group 0 is always the entire lexeme. The trailing context of a rule is not part of it,
+/// and neither are the groups of the trailing context.
+pub struct Captures<'a> {
+    caps: captures::Captures,
+    /// The text of the lexeme
+    text: &'a str,
+    /// The number of groups of the pattern, without the trailing context
+    len: usize,
+    start: (usize, usize),
+    start_idx: usize,
+}
+
+impl<'a> Captures<'a> {
+    #[doc(hidden)]
+    /// Captures of the lexeme `text`. The groups from the one named `__lexr_trail` on are the trailing context.
+    pub fn new(caps: captures::Captures, text: &'a str, start: (usize, usize), start_idx: usize) -> Self {
+        let len = caps.group_info().to_index(PatternID::ZERO, "__lexr_trail").unwrap_or(caps.group_len());
+        Self { caps, text, len, start, start_idx }
+    }
+
+    /// The text of the group with index `i`, if it participated in the match.
+    pub fn get(&self, i: usize) -> Option<&'a str> {
+        self.span(i).map(|span| &self.text[span])
+    }
+
+    /// The text of the named group, if it participated in the match.
+    pub fn name(&self, name: &str) -> Option<&'a str> {
+        self.index_of(name).and_then(|i| self.get(i))
+    }
+
+    /// The location of the group with index `i`, if it participated in the match.
+    pub fn loc(&self, i: usize) -> Option<SrcLoc> {
+        self.span(i).map(|span| self.locate(span.start, span.end))
+    }
+
+    /// The location of the named group, if it participated in the match.
+    pub fn name_loc(&self, name: &str) -> Option<SrcLoc> {
+        self.index_of(name).and_then(|i| self.loc(i))
+    }
+
+    /// The number of groups in the pattern, including group 0.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always false, as group 0 is always present.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The underlying [`regex_automata::util::captures::Captures`], with spans in the remaining source.
+    ///
+    /// Unlike the groups above, these include the trailing context, in the group named `__lexr_trail`.
+    pub fn as_automata_captures(&self) -> &captures::Captures {
+        &self.caps
+    }
+
+    /// The byte range of the group with index `i`, with group 0 ending where the trailing context begins.
+    fn span(&self, i: usize) -> Option<Range<usize>> {
+        if i >= self.len {
+            return None
+        }
+        self.caps.get_group(i).map(|span| span.start..span.end.min(self.text.len()))
+    }
+
+    /// The index of the named group, unless it is part of the trailing context.
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.caps.group_info().to_index(PatternID::ZERO, name).filter(|&i| i < self.len)
+    }
+
+    /// Finds the location of the byte range `[from, to)` of the match.
+    fn locate(&self, from: usize, to: usize) -> SrcLoc {
+        let text = &self.text[..to];
+        let (mut start, mut end, mut next) = (self.start, self.start, self.start);
+        for (i, c) in text.char_indices() {
+            if i == from {
+                start = next;
+            }
+            end = next;
+            next = if c == '\n' { (next.0 + 1, 1) } else { (next.0, next.1 + 1) };
+        }
+
+        if from == to {
+            start = next;
+            end = next;
+        }
+
+        SrcLoc::new(start, end, (self.start_idx + from, self.start_idx + to))
+    }
+}
+
+impl<'a> core::ops::Index<usize> for Captures<'a> {
+    type Output = str;
+
+    /// Panics if the group did not participate in the match.
+    fn index(&self, i: usize) -> &str {
+        self.get(i).unwrap_or_else(|| panic!("no group at index '{}'", i))
+    }
+}
+
+impl<'a, 'n> core::ops::Index<&'n str> for Captures<'a> {
+    type Output = str;
+
+    /// Panics if the group did not participate in the match.
+    fn index(&self, name: &'n str) -> &str {
+        self.name(name).unwrap_or_else(|| panic!("no group named '{}'", name))
+    }
+}
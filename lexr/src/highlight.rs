@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use crate::{Lexer, SrcLoc};
+
+/// A source with the lexemes of its tokens classified for highlighting.
+///
+/// The text between the tokens, like skipped whitespace and comments, is kept as it is,
+/// so rendering the source never loses any of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highlighted<'a> {
+    src: &'a str,
+    /// The byte range and class of each classified lexeme, in order and without overlaps
+    spans: Vec<(usize, usize, String)>,
+}
+
+impl<'a> Highlighted<'a> {
+    /// Classifies the lexemes of `tokens`, which must have been lexed from `src`, with `classify`.
+    ///
+    /// Tokens classified as `None` are left unhighlighted, like the text between tokens.
+    /// Empty tokens are ignored, as are the parts of tokens overlapping an earlier token.
+    pub fn new<T, C: Into<String>>(src: &'a str, tokens: impl IntoIterator<Item = (T, SrcLoc)>, mut classify: impl FnMut(&T) -> Option<C>) -> Self {
+        let mut spans: Vec<(usize, usize, String)> = Vec::new();
+        for (token, loc) in tokens {
+            let (start, end) = loc.get_abs_loc();
+            let start = start.max(spans.last().map_or(0, |span| span.1));
+            let end = end.min(src.len());
+            if start >= end {
+                continue
+            }
+            if let Some(class) = classify(&token) {
+                spans.push((start, end, class.into()));
+            }
+        }
+        Self { src, spans }
+    }
+
+    /// The whole source in order, split into the classified lexemes and the unclassified text between them.
+    pub fn segments(&self) -> impl Iterator<Item = (&'a str, Option<&str>)> + '_ {
+        let mut printed = 0;
+        self.spans.iter()
+            .flat_map(move |(start, end, class)| {
+                let before = (&self.src[printed..*start], None);
+                printed = *end;
+                [before, (&self.src[*start..*end], Some(class.as_str()))]
+            })
+            .chain(std::iter::once_with(move || (&self.src[self.spans.last().map_or(0, |span| span.1)..], None)))
+            .filter(|(text, _)| !text.is_empty())
+    }
+
+    /// Renders the source as HTML, with each classified lexeme in a `<span>` with its class.
+    ///
+    /// The text is escaped but not wrapped, so it is usually placed in a `<pre>` element.
+    pub fn html(&self) -> String {
+        let mut html = String::with_capacity(self.src.len() * 2);
+        for (text, class) in self.segments() {
+            match class {
+                Some(class) => {
+                    html.push_str("<span class=\"");
+                    escape_html(&mut html, class);
+                    html.push_str("\">");
+                    escape_html(&mut html, text);
+                    html.push_str("</span>");
+                }
+                None => escape_html(&mut html, text),
+            }
+        }
+        html
+    }
+
+    /// Renders the source for a terminal, with each classified lexeme colored by the style of its class in `theme`.
+    ///
+    /// The style is reset at the end of each line, so the output can be paged line by line.
+    pub fn ansi(&self, theme: &AnsiTheme) -> String {
+        let mut ansi = String::with_capacity(self.src.len() * 2);
+        for (text, class) in self.segments() {
+            match class.and_then(|class| theme.styles.get(class)) {
+                Some(style) => for (i, line) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        ansi.push('\n');
+                    }
+                    if !line.is_empty() {
+                        ansi.push_str(&format!("\x1b[{}m{}\x1b[0m", style, line));
+                    }
+                },
+                None => ansi.push_str(text),
+            }
+        }
+        ansi
+    }
+}
+
+/// The terminal styles of the classes of a [`Highlighted`] source.
+///
+/// Classes without a style are printed without one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnsiTheme {
+    styles: HashMap<String, String>,
+}
+
+impl AnsiTheme {
+    /// A theme without any styles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Styles `class` with the SGR parameters `style`, like `"1;34"` for bold blue, or `"38;5;208"` for orange.
+    pub fn style(mut self, class: &str, style: &str) -> Self {
+        self.styles.insert(class.to_string(), style.to_string());
+        self
+    }
+}
+
+impl<T, Ite: Iterator<Item = (T, SrcLoc)>> Lexer<T, Ite> {
+    /// Classifies the lexemes of the tokens for highlighting.
+    ///
+    /// `src` must be the input that the lexer was created from. See [`Highlighted::new`] for details.
+    pub fn highlight<C: Into<String>>(self, src: &str, classify: impl FnMut(&T) -> Option<C>) -> Highlighted<'_> {
+        Highlighted::new(src, self, classify)
+    }
+}
+
+fn escape_html(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
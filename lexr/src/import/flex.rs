@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use super::{check_pattern, code_tokens, escape_char, escape_str, group, CodeToken, ImportError, ImportedAction, ImportedLexer, ImportedMode, ImportedRule};
+
+const INITIAL: &str = "INITIAL";
+
+/// Imports a flex (`.l`) specification.
+///
+/// The definitions section can hold options, start conditions and named definitions, which are expanded in the patterns.
+/// Code blocks, and the user code section after the rules, are left out.
+///
+/// A `BEGIN` from the initial start condition is imported as a push, and a `BEGIN(INITIAL)` as a pop,
+/// unless the specification uses the start condition stack itself.
+pub fn import_flex(src: &str) -> Result<ImportedLexer, Vec<ImportError>> {
+    let mut flex = Flex::default();
+    let lines = src.lines().enumerate().map(|(i, line)| (i + 1, line)).collect::<Vec<_>>();
+
+    let mut sections = lines.split(|(_, line)| line.trim_end() == "%%");
+    flex.definitions(sections.next().unwrap_or_default());
+    match sections.next() {
+        Some(rules) => flex.rules(rules),
+        None => flex.errors.push(ImportError::new(lines.len(), "Expected `%%` to start the rules section")),
+    }
+
+    if !flex.errors.is_empty() {
+        return Err(flex.errors)
+    }
+    Ok(flex.lexer())
+}
+
+#[derive(Clone)]
+enum Conditions {
+    /// The initial and the inclusive start conditions
+    Default,
+    All,
+    Only(Vec<String>),
+}
+
+enum FlexAction {
+    Token(String),
+    Skip,
+    Break,
+    Begin(String),
+    Push(String),
+    Pop,
+}
+
+struct FlexRule {
+    line: usize,
+    conditions: Conditions,
+    pattern: Option<String>,
+    trail: Option<String>,
+    bol: bool,
+    /// `None` for `|`, which shares the action of the next rule
+    action: Option<FlexAction>,
+}
+
+#[derive(Default)]
+struct Flex {
+    case_insensitive: bool,
+    definitions: HashMap<String, String>,
+    /// The declared start conditions, and whether they are exclusive
+    conditions: Vec<(String, bool)>,
+    rules: Vec<FlexRule>,
+    errors: Vec<ImportError>,
+}
+
+impl Flex {
+    fn definitions(&mut self, lines: &[(usize, &str)]) {
+        let mut lines = lines.iter();
+        while let Some(&(line, text)) = lines.next() {
+            let trimmed = text.trim();
+            if trimmed.is_empty() || text.starts_with(char::is_whitespace) {
+                continue
+            }
+
+            if trimmed == "%{" || trimmed.starts_with("%top{") {
+                let end = if trimmed == "%{" { "%}" } else { "}" };
+                if !lines.by_ref().any(|(_, text)| text.trim() == end) {
+                    self.errors.push(ImportError::new(line, format!("Expected `{}` to end the code block", end)));
+                }
+            } else if trimmed.starts_with("/*") {
+                if !trimmed.contains("*/") && !lines.by_ref().any(|(_, text)| text.contains("*/")) {
+                    self.errors.push(ImportError::new(line, "Unterminated comment"));
+                }
+            } else if let Some(options) = trimmed.strip_prefix("%option") {
+                for option in options.split_whitespace() {
+                    if matches!(option, "caseless" | "case-insensitive") {
+                        self.case_insensitive = true;
+                    }
+                }
+            } else if let Some(directive) = trimmed.strip_prefix('%') {
+                let (kind, names) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+                let exclusive = match kind {
+                    "s" | "start" => false,
+                    "x" | "exclusive" => true,
+                    "array" | "pointer" => continue,
+                    _ => {
+                        self.errors.push(ImportError::new(line, format!("Unsupported directive %{}", kind)));
+                        continue
+                    }
+                };
+                for name in names.split_whitespace() {
+                    self.conditions.push((name.to_string(), exclusive));
+                }
+            } else {
+                let (name, definition) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+                if definition.trim().is_empty() {
+                    self.errors.push(ImportError::new(line, format!("Expected a pattern for the definition {}", name)));
+                    continue
+                }
+                match self.translate(definition.trim(), line) {
+                    Ok((false, pattern, None)) => { self.definitions.insert(name.to_string(), pattern); }
+                    Ok(_) => self.errors.push(ImportError::new(line, format!("The definition {} can not use `^`, `$` or `/`", name))),
+                    Err(err) => self.errors.push(err),
+                }
+            }
+        }
+    }
+
+    fn rules(&mut self, lines: &[(usize, &str)]) {
+        let mut scopes = Vec::<Conditions>::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let (line, text) = lines[i];
+            i += 1;
+            let trimmed = text.trim();
+            if trimmed.is_empty() || (scopes.is_empty() && text.starts_with(char::is_whitespace)) {
+                continue
+            }
+
+            if trimmed == "%{" {
+                while i < lines.len() && lines[i].1.trim() != "%}" { i += 1 }
+                i += 1;
+                continue
+            }
+            if trimmed.starts_with("/*") {
+                while !lines[i - 1].1.contains("*/") && i < lines.len() { i += 1 }
+                continue
+            }
+            if trimmed == "}" && !scopes.is_empty() {
+                scopes.pop();
+                continue
+            }
+
+            let mut rest = trimmed;
+            let mut conditions = scopes.last().cloned().unwrap_or(Conditions::Default);
+            if rest.starts_with('<') && !rest.starts_with("<<EOF>>") {
+                let Some(end) = rest.find('>') else {
+                    self.errors.push(ImportError::new(line, "Expected `>` to end the start conditions"));
+                    continue
+                };
+                conditions = match &rest[1..end] {
+                    "*" => Conditions::All,
+                    names => Conditions::Only(names.split(',').map(|name| name.trim().to_string()).collect()),
+                };
+                rest = &rest[end + 1..];
+                if rest.trim() == "{" {
+                    scopes.push(conditions);
+                    continue
+                }
+            }
+
+            let (translated, rest) = match rest.strip_prefix("<<EOF>>") {
+                Some(rest) => (Ok((false, None, None)), rest),
+                None => {
+                    let end = pattern_end(rest);
+                    let translated = self.translate(&rest[..end], line).map(|(bol, pattern, trail)| (bol, Some(pattern), trail));
+                    (translated, &rest[end..])
+                }
+            };
+
+            // An action in braces can continue over the following lines
+            let mut code = rest.trim().to_string();
+            while brace_depth(&code) > 0 && i < lines.len() {
+                code.push('\n');
+                code.push_str(lines[i].1);
+                i += 1;
+            }
+            let action = match code.as_str() {
+                "|" => None,
+                code => match self.action(code, line) {
+                    Ok(action) => Some(action),
+                    Err(err) => {
+                        self.errors.push(err);
+                        Some(FlexAction::Skip)
+                    }
+                },
+            };
+
+            let (bol, pattern, trail) = match translated {
+                Ok(translated) => translated,
+                Err(err) => {
+                    self.errors.push(err);
+                    continue
+                }
+            };
+            if let Conditions::Only(names) = &conditions {
+                for name in names {
+                    if name != INITIAL && !self.conditions.iter().any(|(condition, _)| condition == name) {
+                        self.errors.push(ImportError::new(line, format!("Undeclared start condition {}", name)));
+                    }
+                }
+            }
+            self.rules.push(FlexRule { line, conditions, pattern, trail, bol, action });
+        }
+    }
+
+    /// Translates a flex pattern to the syntax of the regex crate.
+    ///
+    /// Returns whether it starts with `^`, the pattern and the trailing context.
+    fn translate(&self, pattern: &str, line: usize) -> Result<(bool, String, Option<String>), ImportError> {
+        let error = |message: String| ImportError::new(line, message);
+        let chars = pattern.chars().collect::<Vec<_>>();
+        let mut regex = String::new();
+        let mut trail_start = None;
+        let mut newline = false;
+        let mut bol = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            match c {
+                '^' if i == 1 => bol = true,
+                '$' if i == chars.len() => newline = true,
+                '"' => {
+                    let mut literal = String::new();
+                    while i < chars.len() && chars[i] != '"' {
+                        i += 1;
+                        literal.push(match chars[i - 1] {
+                            '\\' => escape(&chars, &mut i).map_err(error)?,
+                            c => c,
+                        });
+                    }
+                    if i == chars.len() {
+                        return Err(error("Unterminated string in the pattern".to_string()))
+                    }
+                    i += 1;
+                    regex.push_str(&escape_str(&literal));
+                }
+                '[' => regex.push_str(&class(&chars, &mut i).map_err(error)?),
+                '{' => {
+                    let end = chars[i..].iter().position(|c| *c == '}').ok_or(error("Expected `}` in the pattern".to_string()))?;
+                    let inner = chars[i..i + end].iter().collect::<String>();
+                    i += end + 1;
+                    if inner.starts_with(|c: char| c.is_ascii_digit()) {
+                        regex.push_str(&format!("{{{}}}", inner));
+                    } else if inner == "-" || inner == "+" {
+                        return Err(error(format!("The class operator {{{}}} is not supported", inner)))
+                    } else {
+                        let definition = self.definitions.get(&inner).ok_or(error(format!("Undefined definition {{{}}}", inner)))?;
+                        regex.push_str(&group(definition));
+                    }
+                }
+                '\\' => regex.push_str(&escape_char(escape(&chars, &mut i).map_err(error)?)),
+                '/' if trail_start.is_none() => trail_start = Some(regex.len()),
+                '/' => return Err(error("A pattern can only have one trailing context".to_string())),
+                '.' | '*' | '+' | '?' | '|' | '(' | ')' => regex.push(c),
+                c => regex.push_str(&escape_char(c)),
+            }
+        }
+
+        let trail = match (trail_start, newline) {
+            (Some(_), true) => return Err(error("A pattern can only have one trailing context".to_string())),
+            (Some(start), false) => Some(regex.split_off(start)),
+            (None, true) => Some(r"\n".to_string()),
+            (None, false) => None,
+        };
+        check_pattern(&regex, line)?;
+        if let Some(trail) = &trail {
+            check_pattern(trail, line)?;
+        }
+        Ok((bol, regex, trail))
+    }
+
+    /// Tells what an action does from its C code.
+    fn action(&self, code: &str, line: usize) -> Result<FlexAction, ImportError> {
+        let error = |message: String| ImportError::new(line, message);
+        let tokens = code_tokens(code, false);
+        let ident = |i: usize| match tokens.get(i) {
+            Some(CodeToken::Ident(name)) => Some(name.as_str()),
+            _ => None,
+        };
+        // The argument of a call, or of a statement like `BEGIN X`, with or without parentheses
+        let argument = |i: usize| match tokens.get(i) {
+            Some(CodeToken::Punct('(')) => ident(i + 1),
+            _ => ident(i),
+        };
+
+        let mut actions = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let CodeToken::Ident(name) = token else { continue };
+            actions.push(match name.as_str() {
+                "return" => match (argument(i + 1), tokens.get(i + 1)) {
+                    (Some("0"), _) => FlexAction::Break,
+                    (Some(token), _) if !token.starts_with(|c: char| c.is_ascii_digit()) => FlexAction::Token(token.to_string()),
+                    (_, Some(CodeToken::Literal(_))) => return Err(error("Returning character literals as tokens is not supported".to_string())),
+                    _ => return Err(error(format!("Could not tell the token returned by `{}`", code))),
+                },
+                "yyterminate" => FlexAction::Break,
+                "BEGIN" => match argument(i + 1) {
+                    Some("0") => FlexAction::Begin(INITIAL.to_string()),
+                    Some(condition) => FlexAction::Begin(condition.to_string()),
+                    None => return Err(error("Expected a start condition after BEGIN".to_string())),
+                },
+                "yy_push_state" => match argument(i + 1) {
+                    Some(condition) => FlexAction::Push(condition.to_string()),
+                    None => return Err(error("Expected a start condition to push".to_string())),
+                },
+                "yy_pop_state" => FlexAction::Pop,
+                "REJECT" | "yymore" | "yyless" | "unput" | "input" | "yyinput" => {
+                    return Err(error(format!("{} is not supported", name)))
+                }
+                _ => continue,
+            });
+        }
+
+        match actions.len() {
+            0 => Ok(FlexAction::Skip),
+            1 => Ok(actions.pop().unwrap()),
+            _ => Err(error(format!("Actions that do more than returning a token or changing the start condition are not supported: `{}`", code))),
+        }
+    }
+
+    fn lexer(self) -> ImportedLexer {
+        let uses_stack = self.rules.iter().any(|rule| matches!(rule.action, Some(FlexAction::Push(_) | FlexAction::Pop)));
+        let mut modes = vec![(INITIAL.to_string(), false)];
+        modes.extend(self.conditions.iter().cloned());
+
+        let modes = modes.into_iter().map(|(name, exclusive)| {
+            let mut rules = Vec::new();
+            for (i, rule) in self.rules.iter().enumerate() {
+                let applies = match &rule.conditions {
+                    Conditions::Default => !exclusive,
+                    Conditions::All => true,
+                    Conditions::Only(names) => names.contains(&name),
+                };
+                if !applies {
+                    continue
+                }
+                let Some(action) = self.rules[i..].iter().find_map(|rule| rule.action.as_ref()) else { continue };
+                let action = match action {
+                    FlexAction::Token(token) => ImportedAction::Token(token.clone()),
+                    FlexAction::Skip => ImportedAction::Skip,
+                    FlexAction::Break => ImportedAction::Break,
+                    FlexAction::Push(target) => ImportedAction::Push(target.clone()),
+                    FlexAction::Pop => ImportedAction::Pop,
+                    FlexAction::Begin(target) if uses_stack => ImportedAction::Switch(target.clone()),
+                    // Without the stack, the initial condition is always below the current one
+                    FlexAction::Begin(target) if *target == name => ImportedAction::Skip,
+                    FlexAction::Begin(target) if name == INITIAL => ImportedAction::Push(target.clone()),
+                    FlexAction::Begin(target) if target == INITIAL => ImportedAction::Pop,
+                    FlexAction::Begin(target) => ImportedAction::Switch(target.clone()),
+                };
+                rules.push(ImportedRule {
+                    line: rule.line,
+                    pattern: rule.pattern.clone(),
+                    trail: rule.trail.clone(),
+                    bol: rule.bol,
+                    action,
+                });
+            }
+            ImportedMode { name, rules }
+        }).collect();
+
+        ImportedLexer::new(self.case_insensitive, modes)
+    }
+}
+
+/// The length of the pattern at the start of a rule, which ends at whitespace outside of strings and classes.
+fn pattern_end(rule: &str) -> usize {
+    let mut quoted = false;
+    let mut class = false;
+    let mut chars = rule.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => { chars.next(); }
+            '"' if !class => quoted = !quoted,
+            '[' if !quoted && !class => {
+                class = true;
+                chars.next_if(|(_, c)| *c == '^');
+                chars.next_if(|(_, c)| *c == ']');
+            }
+            '[' if class && chars.peek().map(|(_, c)| *c) == Some(':') => {
+                while chars.next().is_some_and(|(_, c)| c != ']') {}
+            }
+            ']' if class => class = false,
+            c if c.is_whitespace() && !quoted && !class => return i,
+            _ => {}
+        }
+    }
+    rule.len()
+}
+
+/// Translates a character class, after its `[`.
+fn class(chars: &[char], i: &mut usize) -> Result<String, String> {
+    let mut class = String::from("[");
+    if chars.get(*i) == Some(&'^') {
+        class.push('^');
+        *i += 1;
+    }
+    if chars.get(*i) == Some(&']') {
+        class.push_str(r"\]");
+        *i += 1;
+    }
+    while let Some(&c) = chars.get(*i) {
+        *i += 1;
+        match c {
+            ']' => {
+                class.push(']');
+                return Ok(class)
+            }
+            '[' if chars.get(*i) == Some(&':') => {
+                let end = chars[*i..].iter().position(|c| *c == ']').ok_or("Unterminated character class")?;
+                class.push('[');
+                class.extend(&chars[*i..=*i + end]);
+                *i += end + 1;
+            }
+            '\\' => class.push_str(&escape_char(escape(chars, i)?)),
+            '-' => class.push('-'),
+            c => class.push_str(&escape_char(c)),
+        }
+    }
+    Err("Unterminated character class".to_string())
+}
+
+/// Decodes an escape sequence, after its `\`.
+fn escape(chars: &[char], i: &mut usize) -> Result<char, String> {
+    let c = *chars.get(*i).ok_or("Expected a character after `\\`")?;
+    *i += 1;
+    let digits = |i: &mut usize, radix: u32, max: usize| {
+        let count = chars[*i..].iter().take(max).take_while(|c| c.is_digit(radix)).count();
+        let code = chars[*i..*i + count].iter().collect::<String>();
+        *i += count;
+        u32::from_str_radix(&code, radix).ok().and_then(char::from_u32)
+    };
+    Ok(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'f' => '\x0C',
+        'v' => '\x0B',
+        'a' => '\x07',
+        'b' => '\x08',
+        '0'..='7' => {
+            *i -= 1;
+            digits(i, 8, 3).ok_or("Invalid octal escape")?
+        }
+        'x' => digits(i, 16, 2).ok_or("Invalid hexadecimal escape")?,
+        c => c,
+    })
+}
+
+/// How many more braces are opened than closed in C code, outside of strings and comments.
+fn brace_depth(code: &str) -> i32 {
+    code_tokens(code, false).iter().map(|token| match token {
+        CodeToken::Punct('{') => 1,
+        CodeToken::Punct('}') => -1,
+        _ => 0,
+    }).sum()
+}
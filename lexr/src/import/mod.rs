@@ -0,0 +1,341 @@
+//! Converts lexer specifications written for flex and ocamllex.
+//!
+//! A specification is imported with [`import_flex`] or [`import_ocamllex`], which translate the patterns to the syntax of the `regex` crate.
+//! The [`ImportedLexer`] can then be emitted as `lex_rule!` source with [`to_lex_rule`](ImportedLexer::to_lex_rule),
+//! or as a specification for the `lexr` command line tool, which is loaded with a [`LexerBuilder`](crate::LexerBuilder), with [`to_spec`](ImportedLexer::to_spec).
+//!
+//! The actions of the rules are source code, which is only understood as far as to tell which token is produced,
+//! whether the lexeme is skipped and how the start condition changes. Anything else in an action is left out.
+//!
+//! Both tools select the longest match, while lexr selects the first rule that matches.
+//! The rules are kept in their order, so a rule that matches a prefix of what a later rule matches,
+//! like a keyword declared before identifiers, is selected where the other tools select the later rule.
+//! Every such rule is reported in [`ImportedLexer::warnings`], naming the line of the later rule, and has to be reviewed after importing.
+
+mod flex;
+mod ocamllex;
+
+use std::fmt::{Display, Write};
+
+use crate::analysis::{self, Candidate};
+
+pub use flex::import_flex;
+pub use ocamllex::import_ocamllex;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A construct that could not be imported or emitted.
+pub struct ImportError {
+    /// The line of the construct in the imported specification
+    pub line: usize,
+    pub message: String,
+}
+
+impl ImportError {
+    pub(crate) fn new(line: usize, message: impl Into<String>) -> Self {
+        Self { line, message: message.into() }
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A lexer imported from another tool.
+pub struct ImportedLexer {
+    pub case_insensitive: bool,
+    /// The modes of the lexer, which are the start conditions of flex and the entry points of ocamllex.
+    /// The first mode is the initial mode, named [`LexerBuilder::INITIAL`](crate::LexerBuilder::INITIAL)
+    pub modes: Vec<ImportedMode>,
+    /// The rules that are selected for a prefix of an input that the imported tool selects a later rule for
+    pub warnings: Vec<ImportError>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The rules of a mode, in the order they are tried.
+pub struct ImportedMode {
+    pub name: String,
+    pub rules: Vec<ImportedRule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A rule of an imported lexer.
+pub struct ImportedRule {
+    /// The line of the rule in the imported specification
+    pub line: usize,
+    /// The pattern in the syntax of the `regex` crate, or `None` for the end of the input
+    pub pattern: Option<String>,
+    /// The trailing context in the syntax of the `regex` crate
+    pub trail: Option<String>,
+    /// Whether the rule only matches at the beginning of a line
+    pub bol: bool,
+    pub action: ImportedAction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// What an imported rule does, as far as it can be told from its action.
+pub enum ImportedAction {
+    /// Produces the token with this name
+    Token(String),
+    Skip,
+    Break,
+    Push(String),
+    Pop,
+    Switch(String),
+}
+
+impl ImportedLexer {
+    pub(crate) fn new(case_insensitive: bool, modes: Vec<ImportedMode>) -> Self {
+        let mut warnings = Vec::<ImportError>::new();
+        for mode in &modes {
+            for warning in longest_match_warnings(mode, case_insensitive) {
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+        Self { case_insensitive, modes, warnings }
+    }
+
+    /// Emits the lexer as a specification for the `lexr` command line tool.
+    pub fn to_spec(&self) -> String {
+        let mut spec = String::new();
+        if self.case_insensitive {
+            spec.push_str("flags case_insensitive\n");
+        }
+
+        for (i, mode) in self.modes.iter().enumerate() {
+            if i > 0 {
+                writeln!(spec, "\nmode {}", mode.name).unwrap();
+            }
+            for rule in &mode.rules {
+                let action = match &rule.action {
+                    ImportedAction::Token(name) => name.clone(),
+                    ImportedAction::Skip => "skip".to_string(),
+                    ImportedAction::Break => "break".to_string(),
+                    ImportedAction::Push(mode) => format!("push {}", mode),
+                    ImportedAction::Pop => "pop".to_string(),
+                    ImportedAction::Switch(mode) => format!("switch {}", mode),
+                };
+                writeln!(spec, "{} => {}", rule.pattern_source(), action).unwrap();
+            }
+        }
+
+        spec
+    }
+
+    /// Emits the lexer as a token enum and a `lex_rule!` named `name`.
+    ///
+    /// Every mode but the initial one becomes a sub rule, which is called when the mode is pushed and breaks when it is popped.
+    /// As a sub rule can not produce tokens for the rule calling it, modes that produce tokens, and switching modes, can not be emitted.
+    pub fn to_lex_rule(&self, name: &str) -> Result<String, Vec<ImportError>> {
+        let mut errors = Vec::new();
+        let mut tokens = Vec::<&str>::new();
+        let mut rules = String::new();
+
+        for (i, mode) in self.modes.iter().enumerate() {
+            let (lexer, token) = if i == 0 { (name.to_string(), "Token") } else { (sub_rule(name, &mode.name), "()") };
+            writeln!(rules, "\nlexr::lex_rule!{{pub {} -> {} {{", lexer, token).unwrap();
+
+            for rule in &mode.rules {
+                let action = match &rule.action {
+                    ImportedAction::Token(token) if i == 0 => {
+                        if !tokens.contains(&token.as_str()) {
+                            tokens.push(token);
+                        }
+                        format!("|_| Token::{}", token)
+                    }
+                    ImportedAction::Token(_) => {
+                        errors.push(ImportError::new(rule.line, format!("Mode {} produces tokens, which a sub rule can not return", mode.name)));
+                        continue
+                    }
+                    ImportedAction::Skip => "|_| continue".to_string(),
+                    ImportedAction::Break if i == 0 => "|_| break".to_string(),
+                    ImportedAction::Break => {
+                        errors.push(ImportError::new(rule.line, "Stopping from within a sub rule is not supported"));
+                        continue
+                    }
+                    ImportedAction::Push(mode) => format!("|_, buf| {{ {}(buf).deplete(); continue }}", sub_rule(name, mode)),
+                    ImportedAction::Pop if i == 0 => "|_| continue".to_string(),
+                    ImportedAction::Pop => "|_| break".to_string(),
+                    ImportedAction::Switch(mode) => {
+                        errors.push(ImportError::new(rule.line, format!("Switching to mode {} is only supported by specs", mode)));
+                        continue
+                    }
+                };
+                writeln!(rules, "    {} => {},", rule.pattern_source(), action).unwrap();
+            }
+            rules.push_str("}}\n");
+        }
+
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+
+        let mut source = String::new();
+        writeln!(source, "#[allow(non_camel_case_types, clippy::upper_case_acronyms)]").unwrap();
+        writeln!(source, "#[derive(Clone, Debug, PartialEq)]").unwrap();
+        writeln!(source, "pub enum Token {{").unwrap();
+        for token in tokens {
+            writeln!(source, "    {},", token).unwrap();
+        }
+        writeln!(source, "}}").unwrap();
+        if self.case_insensitive {
+            source.push_str(&rules.replacen("lexr::lex_rule!{", "lexr::lex_rule!{#[case_insensitive] ", self.modes.len()));
+        } else {
+            source.push_str(&rules);
+        }
+        Ok(source)
+    }
+}
+
+impl ImportedRule {
+    /// The pattern as written in a spec or a `lex_rule!`
+    fn pattern_source(&self) -> String {
+        let mut source = String::new();
+        if self.bol {
+            source.push_str("^ ");
+        }
+        match &self.pattern {
+            Some(pattern) => source.push_str(&raw_literal(pattern)),
+            None => source.push_str("eof"),
+        }
+        if let Some(trail) = &self.trail {
+            write!(source, " / {}", raw_literal(trail)).unwrap();
+        }
+        source
+    }
+}
+
+/// Warns about each rule of the mode that matches a prefix of an input that a later rule matches,
+/// as the longest match selects the later rule for it.
+fn longest_match_warnings(mode: &ImportedMode, case_insensitive: bool) -> Vec<ImportError> {
+    let regexes = mode.rules.iter().map(|rule| {
+        let pattern = rule.pattern.as_ref()?;
+        let regex = match &rule.trail {
+            Some(trail) => format!("(?:{})(?:{})", pattern, trail),
+            None => pattern.clone(),
+        };
+        analysis::build_dfa(&Candidate { regex: &regex, case_insensitive, dot_all: false })
+    }).collect::<Vec<_>>();
+
+    let mut warnings = Vec::new();
+    for (i, rule) in mode.rules.iter().enumerate() {
+        let Some(earlier) = &regexes[i] else { continue };
+        let shadowed = mode.rules.iter().zip(&regexes).skip(i + 1).find_map(|(later, regex)| {
+            Some((later, analysis::longer_match(regex.as_ref()?, earlier)?))
+        });
+        if let Some((later, (prefix, input))) = shadowed {
+            warnings.push(ImportError::new(rule.line, format!(
+                "The rule matches {:?}, a prefix of {:?}, which the longest match selects the rule on line {} for, while lexr selects this rule as it is tried first",
+                prefix, input, later.line,
+            )));
+        }
+    }
+    warnings
+}
+
+fn sub_rule(lexer: &str, mode: &str) -> String {
+    format!("{}_{}", lexer, mode.to_lowercase())
+}
+
+/// A raw string literal, with enough `#` to hold any quotes in `text`.
+fn raw_literal(text: &str) -> String {
+    let hashes = (0..).find(|n| !text.contains(&format!("\"{}", "#".repeat(*n)))).unwrap();
+    format!("r{0}\"{1}\"{0}", "#".repeat(hashes), text)
+}
+
+/// Escapes a character for a pattern or a class, writing control characters as escapes so patterns stay on one line.
+fn escape_char(c: char) -> String {
+    match c {
+        '\n' => r"\n".to_string(),
+        '\t' => r"\t".to_string(),
+        '\r' => r"\r".to_string(),
+        c if c.is_control() => format!(r"\x{{{:X}}}", c as u32),
+        c => regex_syntax::escape(&c.to_string()),
+    }
+}
+
+/// Escapes a string for a pattern.
+fn escape_str(s: &str) -> String {
+    s.chars().map(escape_char).collect()
+}
+
+/// Wraps a pattern in a group, unless it is a single item that repetitions and concatenations apply to as a whole.
+fn group(pattern: &str) -> String {
+    use regex_syntax::ast::Ast;
+    match regex_syntax::ast::parse::Parser::new().parse(pattern) {
+        Ok(Ast::Literal(_) | Ast::Dot(_) | Ast::ClassBracketed(_) | Ast::ClassPerl(_) | Ast::ClassUnicode(_) | Ast::Group(_)) => pattern.to_string(),
+        _ => format!("(?:{})", pattern),
+    }
+}
+
+/// Checks that a translated pattern is valid in the syntax of the `regex` crate.
+fn check_pattern(pattern: &str, line: usize) -> Result<(), ImportError> {
+    regex_syntax::Parser::new().parse(pattern)
+        .map(|_| ())
+        .map_err(|err| ImportError::new(line, format!("The pattern translates to the invalid regex {}: {}", pattern, err)))
+}
+
+/// Splits the actions of rules into tokens, to tell what they do.
+#[derive(Clone, Debug, PartialEq)]
+enum CodeToken {
+    Ident(String),
+    Literal(String),
+    Punct(char),
+}
+
+/// Tokenizes C or OCaml code, skipping comments and whitespace.
+fn code_tokens(code: &str, ocaml: bool) -> Vec<CodeToken> {
+    let chars = code.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if !ocaml && c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' { i += 1 }
+        } else if !ocaml && c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) { i += 1 }
+            i += 2;
+        } else if ocaml && c == '(' && chars.get(i + 1) == Some(&'*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '(' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&')') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 { break }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || (ocaml && chars[i] == '\'')) { i += 1 }
+            tokens.push(CodeToken::Ident(chars[start..i].iter().collect()));
+        } else if c == '"' || (c == '\'' && (!ocaml || chars.get(i + 2) == Some(&'\'') || chars.get(i + 1) == Some(&'\\'))) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' { i += 1 }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(CodeToken::Literal(chars[start..i.min(chars.len())].iter().collect()));
+        } else {
+            tokens.push(CodeToken::Punct(c));
+            i += 1;
+        }
+    }
+    tokens
+}
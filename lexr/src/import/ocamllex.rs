@@ -0,0 +1,563 @@
+use std::collections::HashMap;
+use super::{check_pattern, code_tokens, escape_char, escape_str, CodeToken, ImportError, ImportedAction, ImportedLexer, ImportedMode, ImportedRule};
+
+const INITIAL: &str = "INITIAL";
+
+/// Imports an ocamllex (`.mll`) specification.
+///
+/// Every entry point becomes a mode, where the first is the initial mode.
+/// An action that calls the entry point it belongs to skips the lexeme, while calling another entry point first pushes it,
+/// and returning `()` pops it. A tail call to another entry point switches to it, if that entry point never returns tokens.
+///
+/// Rules that raise exceptions other than `End_of_file` are left out, as lexr reports input that no rule matches as a [`LexError`](crate::LexError).
+pub fn import_ocamllex(src: &str) -> Result<ImportedLexer, Vec<ImportError>> {
+    let tokens = tokenize(src).map_err(|err| vec![err])?;
+    let mut parser = Parser { tokens, pos: 0, definitions: HashMap::new(), end: src.lines().count() };
+    let entries = parser.parse().map_err(|err| vec![err])?;
+
+    let names = entries.iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>();
+    let mut errors = Vec::new();
+    let mut actions = Vec::new();
+    for entry in &entries {
+        actions.push(entry.clauses.iter().map(|clause| action(&clause.action, &entry.name, &names, clause.line)).collect::<Vec<_>>());
+    }
+    let returns_tokens = |entry: usize| actions[entry].iter().any(|action| matches!(action, Ok(Some(ImportedAction::Token(_)))));
+    // The first entry point is the initial mode
+    let rename = |name: &str| if name == names[0] { INITIAL.to_string() } else { name.to_string() };
+
+    let mut modes = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let mut rules = Vec::new();
+        for (clause, action) in entry.clauses.iter().zip(&actions[i]) {
+            let action = match action {
+                Ok(Some(action)) => action.clone(),
+                Ok(None) => continue,
+                Err(err) => {
+                    errors.push(err.clone());
+                    continue
+                }
+            };
+            let action = match action {
+                ImportedAction::Switch(target) if returns_tokens(names.iter().position(|name| *name == target).unwrap()) => {
+                    errors.push(ImportError::new(clause.line, format!("Calling the entry point {}, which returns tokens, is not supported", target)));
+                    continue
+                }
+                ImportedAction::Push(target) => ImportedAction::Push(rename(&target)),
+                ImportedAction::Switch(target) => ImportedAction::Switch(rename(&target)),
+                action => action,
+            };
+            rules.push(ImportedRule { line: clause.line, pattern: clause.pattern.clone(), trail: None, bol: false, action });
+        }
+        modes.push(ImportedMode { name: rename(&entry.name), rules });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+    Ok(ImportedLexer::new(false, modes))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Code(String),
+    Char(char),
+    Str(String),
+    Ident(String),
+    Punct(char),
+}
+
+/// Splits a specification into tokens with their lines, skipping comments.
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, ImportError> {
+    let chars = src.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let start = line;
+        let error = |message: &str| ImportError::new(start, message);
+        let c = chars[i];
+        if c == '(' && chars.get(i + 1) == Some(&'*') {
+            i = skip_comment(&chars, i, &mut line).ok_or(error("Unterminated comment"))?;
+        } else if c == '{' {
+            let mut depth = 0;
+            let begin = i + 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(error("Unterminated code block")),
+                    Some('{') => depth += 1,
+                    Some('}') => depth -= 1,
+                    Some('\n') => line += 1,
+                    Some('"') => i = skip_string(&chars, i, &mut line).ok_or(error("Unterminated string"))? - 1,
+                    Some('(') if chars.get(i + 1) == Some(&'*') => i = skip_comment(&chars, i, &mut line).ok_or(error("Unterminated comment"))? - 1,
+                    Some('\'') if chars.get(i + 2) == Some(&'\'') => i += 2,
+                    _ => {}
+                }
+                i += 1;
+                if depth == 0 { break }
+            }
+            tokens.push((start, Token::Code(chars[begin..i - 1].iter().collect())));
+        } else if c == '\'' {
+            i += 1;
+            let value = match chars.get(i) {
+                Some('\\') => {
+                    i += 1;
+                    escape(&chars, &mut i).ok_or(error("Invalid escape in character literal"))?
+                }
+                Some(&c) => {
+                    i += 1;
+                    c
+                }
+                None => return Err(error("Unterminated character literal")),
+            };
+            if chars.get(i) != Some(&'\'') {
+                return Err(error("Unterminated character literal"))
+            }
+            i += 1;
+            tokens.push((start, Token::Char(value)));
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(error("Unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        i += 1;
+                        // A backslash at the end of a line continues the string after the indentation of the next
+                        if chars.get(i) == Some(&'\n') {
+                            while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+                                if chars[i] == '\n' { line += 1 }
+                                i += 1;
+                            }
+                            continue
+                        }
+                        value.push(escape(&chars, &mut i).ok_or(error("Invalid escape in string"))?);
+                    }
+                    Some(&c) => {
+                        if c == '\n' { line += 1 }
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((start, Token::Str(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let begin = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '\'') { i += 1 }
+            tokens.push((start, Token::Ident(chars[begin..i].iter().collect())));
+        } else {
+            if c == '\n' { line += 1 }
+            if !c.is_whitespace() {
+                tokens.push((start, Token::Punct(c)));
+            }
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Skips a nested comment starting at `i`, returning the index after it.
+fn skip_comment(chars: &[char], mut i: usize, line: &mut usize) -> Option<usize> {
+    let mut depth = 0;
+    loop {
+        match (chars.get(i)?, chars.get(i + 1)) {
+            ('(', Some('*')) => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', Some(')')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 { return Some(i) }
+            }
+            (c, _) => {
+                if *c == '\n' { *line += 1 }
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Skips a string starting at `i`, returning the index after it.
+fn skip_string(chars: &[char], mut i: usize, line: &mut usize) -> Option<usize> {
+    i += 1;
+    loop {
+        match chars.get(i)? {
+            '"' => return Some(i + 1),
+            '\\' => i += 1,
+            '\n' => *line += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Decodes an escape sequence of OCaml, after its `\`.
+fn escape(chars: &[char], i: &mut usize) -> Option<char> {
+    let c = *chars.get(*i)?;
+    *i += 1;
+    let code = |i: &mut usize, radix: u32, count: usize| {
+        let digits = chars.get(*i..*i + count)?.iter().collect::<String>();
+        *i += count;
+        u32::from_str_radix(&digits, radix).ok().and_then(char::from_u32)
+    };
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        'b' => Some('\x08'),
+        ' ' | '\\' | '\'' | '"' => Some(c),
+        '0'..='9' => {
+            *i -= 1;
+            code(i, 10, 3)
+        }
+        'x' => code(i, 16, 2),
+        'o' => code(i, 8, 3),
+        _ => None,
+    }
+}
+
+/// A translated regular expression.
+#[derive(Clone)]
+struct Re {
+    text: String,
+    /// The pattern as a class, if it matches a single character, which is needed for `#`
+    set: Option<String>,
+    /// How tightly the pattern binds: 0 for alternations, 1 for sequences, and 2 for atoms
+    precedence: u8,
+    eof: bool,
+}
+
+impl Re {
+    fn atom(text: String) -> Self {
+        Self { text, set: None, precedence: 2, eof: false }
+    }
+
+    fn set(set: String) -> Self {
+        Self { text: set.clone(), set: Some(set), precedence: 2, eof: false }
+    }
+
+    /// The pattern, wrapped in a group if it binds less tightly than `precedence`
+    fn wrapped(&self, precedence: u8) -> String {
+        if self.precedence < precedence { format!("(?:{})", self.text) } else { self.text.clone() }
+    }
+}
+
+struct Entry {
+    name: String,
+    clauses: Vec<Clause>,
+}
+
+struct Clause {
+    line: usize,
+    pattern: Option<String>,
+    action: String,
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    definitions: HashMap<String, Re>,
+    /// The last line, for errors at the end of the input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(line, _)| *line)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ImportError {
+        ImportError::new(self.line(), message)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).map(|(_, token)| token.clone())
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        let found = self.peek() == Some(&token);
+        if found { self.pos += 1 }
+        found
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(name)) if name == keyword)
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, ImportError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error(format!("Expected {}", what))),
+        }
+    }
+
+    fn parse(&mut self) -> Result<Vec<Entry>, ImportError> {
+        if matches!(self.peek(), Some(Token::Code(_))) {
+            self.pos += 1;
+        }
+
+        while self.is_keyword("let") {
+            self.pos += 1;
+            let name = self.ident("the name of a definition")?;
+            if !self.eat(Token::Punct('=')) {
+                return Err(self.error(format!("Expected `=` after let {}", name)))
+            }
+            let line = self.line();
+            let re = self.regexp()?;
+            if re.eof {
+                return Err(ImportError::new(line, "eof can not be used in a definition"))
+            }
+            self.definitions.insert(name, re);
+        }
+
+        if !self.is_keyword("rule") {
+            return Err(self.error("Expected `rule`"))
+        }
+        let mut entries = Vec::new();
+        while self.is_keyword("rule") || self.is_keyword("and") {
+            self.pos += 1;
+            let name = self.ident("the name of an entry point")?;
+            // The arguments of the entry point are passed on by the actions, and are not needed for the patterns
+            while matches!(self.peek(), Some(Token::Ident(_))) {
+                self.pos += 1;
+            }
+            if !self.eat(Token::Punct('=')) {
+                return Err(self.error(format!("Expected `=` after rule {}", name)))
+            }
+            if self.is_keyword("shortest") {
+                return Err(self.error("Shortest match is not supported"))
+            }
+            if !self.is_keyword("parse") {
+                return Err(self.error("Expected `parse`"))
+            }
+            self.pos += 1;
+
+            let mut clauses = Vec::new();
+            self.eat(Token::Punct('|'));
+            loop {
+                let line = self.line();
+                let re = self.regexp()?;
+                let Some(Token::Code(action)) = self.next() else {
+                    self.pos -= 1;
+                    return Err(self.error("Expected an action in braces"))
+                };
+                let pattern = if re.eof { None } else {
+                    check_pattern(&re.text, line)?;
+                    Some(re.text)
+                };
+                clauses.push(Clause { line, pattern, action });
+                if !self.eat(Token::Punct('|')) {
+                    break
+                }
+            }
+            entries.push(Entry { name, clauses });
+        }
+
+        if matches!(self.peek(), Some(Token::Code(_))) {
+            self.pos += 1;
+        }
+        if self.peek().is_some() {
+            return Err(self.error("Expected the end of the specification"))
+        }
+        Ok(entries)
+    }
+
+    fn regexp(&mut self) -> Result<Re, ImportError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat(Token::Punct('|')) {
+            alternatives.push(self.sequence()?);
+        }
+        // Bindings with `as` only name parts of the lexeme for the action
+        while self.is_keyword("as") {
+            self.pos += 1;
+            self.ident("a name after `as`")?;
+        }
+
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap())
+        }
+        if alternatives.iter().any(|re| re.eof) {
+            return Err(self.error("eof can only be used as a whole pattern"))
+        }
+        let text = alternatives.iter().map(|re| re.text.as_str()).collect::<Vec<_>>().join("|");
+        Ok(Re { text, set: None, precedence: 0, eof: false })
+    }
+
+    fn sequence(&mut self) -> Result<Re, ImportError> {
+        let mut items = vec![self.postfix()?];
+        while matches!(self.peek(), Some(Token::Char(_) | Token::Str(_) | Token::Punct('[' | '(')))
+            || matches!(self.peek(), Some(Token::Ident(name)) if !matches!(name.as_str(), "as" | "let" | "rule" | "and")) {
+            items.push(self.postfix()?);
+        }
+
+        if items.len() == 1 {
+            return Ok(items.pop().unwrap())
+        }
+        if items.iter().any(|re| re.eof) {
+            return Err(self.error("eof can only be used as a whole pattern"))
+        }
+        let text = items.iter().map(|re| re.wrapped(1)).collect::<String>();
+        Ok(Re { text, set: None, precedence: 1, eof: false })
+    }
+
+    fn postfix(&mut self) -> Result<Re, ImportError> {
+        let mut re = self.difference()?;
+        while let Some(Token::Punct(op @ ('*' | '+' | '?'))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            re = Re::atom(format!("{}{}", re.wrapped(2), op));
+        }
+        Ok(re)
+    }
+
+    fn difference(&mut self) -> Result<Re, ImportError> {
+        let mut re = self.atom()?;
+        while self.eat(Token::Punct('#')) {
+            let other = self.atom()?;
+            match (&re.set, &other.set) {
+                (Some(left), Some(right)) => re = Re::set(format!("[{}--{}]", left, right)),
+                _ => return Err(self.error("`#` can only be used between character sets")),
+            }
+        }
+        Ok(re)
+    }
+
+    fn atom(&mut self) -> Result<Re, ImportError> {
+        match self.next() {
+            Some(Token::Char(c)) => Ok(Re { text: escape_char(c), set: Some(format!("[{}]", escape_char(c))), precedence: 2, eof: false }),
+            Some(Token::Str(s)) => Ok(Re { text: escape_str(&s), set: None, precedence: if s.chars().count() == 1 { 2 } else { 1 }, eof: false }),
+            Some(Token::Ident(name)) if name == "_" => Ok(Re { text: "(?s:.)".to_string(), set: Some(r"[\x00-\x{10FFFF}]".to_string()), precedence: 2, eof: false }),
+            Some(Token::Ident(name)) if name == "eof" => Ok(Re { text: String::new(), set: None, precedence: 2, eof: true }),
+            Some(Token::Ident(name)) => match self.definitions.get(&name) {
+                Some(re) => Ok(re.clone()),
+                None => {
+                    self.pos -= 1;
+                    Err(self.error(format!("Undefined regular expression {}", name)))
+                }
+            },
+            Some(Token::Punct('(')) => {
+                let re = self.regexp()?;
+                if !self.eat(Token::Punct(')')) {
+                    return Err(self.error("Expected `)`"))
+                }
+                Ok(re)
+            }
+            Some(Token::Punct('[')) => self.set(),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("Expected a regular expression"))
+            }
+        }
+    }
+
+    /// Translates a character set, after its `[`.
+    fn set(&mut self) -> Result<Re, ImportError> {
+        let mut set = String::from("[");
+        if self.eat(Token::Punct('^')) {
+            set.push('^');
+        }
+        loop {
+            match self.next() {
+                Some(Token::Punct(']')) => break,
+                Some(Token::Char(c)) => {
+                    set.push_str(&escape_char(c));
+                    if self.eat(Token::Punct('-')) {
+                        let Some(Token::Char(end)) = self.next() else {
+                            self.pos -= 1;
+                            return Err(self.error("Expected a character to end the range"))
+                        };
+                        set.push('-');
+                        set.push_str(&escape_char(end));
+                    }
+                }
+                Some(Token::Str(s)) => s.chars().for_each(|c| set.push_str(&escape_char(c))),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("Expected a character or `]` in the character set"))
+                }
+            }
+        }
+        set.push(']');
+        Ok(Re::set(set))
+    }
+}
+
+/// Tells what an action does from its OCaml code, or `None` for rules that are left out.
+fn action(code: &str, entry: &str, entries: &[&str], line: usize) -> Result<Option<ImportedAction>, ImportError> {
+    let error = |message: String| ImportError::new(line, message);
+    let tokens = code_tokens(code, true);
+
+    // The statements of the action, split at `;` outside of groups
+    let mut statements = vec![Vec::new()];
+    let mut depth = 0;
+    for token in tokens {
+        match &token {
+            CodeToken::Punct('(' | '[' | '{') => depth += 1,
+            CodeToken::Ident(word) if word == "begin" => depth += 1,
+            CodeToken::Punct(')' | ']' | '}') => depth -= 1,
+            CodeToken::Ident(word) if word == "end" => depth -= 1,
+            CodeToken::Punct(';') if depth == 0 => {
+                statements.push(Vec::new());
+                continue
+            }
+            _ => {}
+        }
+        statements.last_mut().unwrap().push(token);
+    }
+    statements.retain(|statement| !statement.is_empty());
+    let Some(mut last) = statements.pop() else {
+        return Err(error("Expected an expression in the action".to_string()))
+    };
+    // Only the body of a `let ... in` tells what the action returns
+    if let Some(body) = last.iter().rposition(|token| *token == CodeToken::Ident("in".to_string())) {
+        last.drain(..=body);
+    }
+
+    let calls = |statement: &[CodeToken]| match statement.first() {
+        Some(CodeToken::Ident(name)) if entries.contains(&name.as_str()) => Some(name.clone()),
+        _ => None,
+    };
+    let pushed = statements.iter().find_map(|statement| calls(statement));
+
+    if let Some(target) = calls(&last) {
+        return Ok(Some(match pushed {
+            Some(pushed) if target == entry => ImportedAction::Push(pushed),
+            Some(_) => return Err(error(format!("Calling more than one entry point is not supported: `{}`", code.trim()))),
+            None if target == entry => ImportedAction::Skip,
+            None => ImportedAction::Switch(target),
+        }))
+    }
+    if pushed.is_some() {
+        return Err(error(format!("Calling an entry point before returning is not supported: `{}`", code.trim())))
+    }
+
+    match last.as_slice() {
+        [CodeToken::Punct('('), CodeToken::Punct(')')] => Ok(Some(ImportedAction::Pop)),
+        [CodeToken::Ident(raise), rest @ ..] if raise == "raise" => match rest.iter().find(|token| !matches!(token, CodeToken::Punct('('))) {
+            Some(CodeToken::Ident(exception)) if exception == "End_of_file" || exception == "Eof" => Ok(Some(ImportedAction::Break)),
+            _ => Ok(None),
+        },
+        _ => {
+            // The constructor of the token is the last part of a path like `Parser.INT`
+            let mut path = last.as_slice();
+            while let [CodeToken::Ident(_), CodeToken::Punct('.'), rest @ ..] = path {
+                path = rest;
+            }
+            match path.first() {
+                Some(CodeToken::Ident(name)) if name.starts_with(|c: char| c.is_uppercase()) => Ok(Some(ImportedAction::Token(name.clone()))),
+                _ => Err(error(format!("Could not tell the token produced by `{}`", code.trim()))),
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The average number of keywords in each bucket of the table
+const BUCKET_SIZE: usize = 4;
+
+#[doc(hidden)]
+/// A perfect hash table of keywords, mapping each keyword to its index.
+///
+/// The table is built with the hash and displace algorithm.
+/// Each keyword is hashed to a bucket, and each bucket is given a displacement
+/// that places all of its keywords in distinct slots.
+pub struct KeywordTable {
+    keywords: Vec<String>,
+    case_insensitive: bool,
+    seed: u64,
+    displacements: Vec<(u64, u64)>,
+    slots: Vec<usize>,
+}
+
+impl KeywordTable {
+    pub fn new(keywords: &[&str], case_insensitive: bool) -> Self {
+        let keywords = keywords.iter()
+            .map(|keyword| if case_insensitive { keyword.to_lowercase() } else { keyword.to_string() })
+            .collect::<Vec<_>>();
+
+        for (i, keyword) in keywords.iter().enumerate() {
+            if keywords[..i].contains(keyword) {
+                panic!("Duplicate keyword '{}'", keyword);
+            }
+        }
+
+        (0..).find_map(|seed| Self::try_build(&keywords, case_insensitive, seed)).unwrap()
+    }
+
+    fn try_build(keywords: &[String], case_insensitive: bool, seed: u64) -> Option<Self> {
+        let len = keywords.len() as u64;
+        let bucket_count = keywords.len().div_ceil(BUCKET_SIZE);
+        let hashes = keywords.iter().map(|keyword| hash(keyword, seed)).collect::<Vec<_>>();
+
+        let mut buckets = vec![Vec::new(); bucket_count];
+        for (i, (bucket, _, _)) in hashes.iter().enumerate() {
+            buckets[(*bucket % bucket_count as u64) as usize].push(i);
+        }
+
+        // The largest buckets are placed first, while there are most free slots
+        let mut order = (0..bucket_count).collect::<Vec<_>>();
+        order.sort_by_key(|b| core::cmp::Reverse(buckets[*b].len()));
+
+        let mut displacements = vec![(0, 0); bucket_count];
+        let mut slots = vec![usize::MAX; keywords.len()];
+        let mut placed = Vec::with_capacity(BUCKET_SIZE);
+        'buckets: for b in order {
+            for d1 in 0..len {
+                'displace: for d2 in 0..len {
+                    placed.clear();
+                    for &i in &buckets[b] {
+                        let (_, f1, f2) = hashes[i];
+                        let slot = displace(f1, f2, (d1, d2), len);
+                        if slots[slot] != usize::MAX || placed.iter().any(|(s, _)| *s == slot) {
+                            continue 'displace
+                        }
+                        placed.push((slot, i));
+                    }
+
+                    for &(slot, i) in &placed {
+                        slots[slot] = i;
+                    }
+                    displacements[b] = (d1, d2);
+                    continue 'buckets
+                }
+            }
+
+            return None
+        }
+
+        Some(Self { keywords: keywords.to_vec(), case_insensitive, seed, displacements, slots })
+    }
+
+    /// The index of the keyword, if `word` is one.
+    pub fn get(&self, word: &str) -> Option<usize> {
+        if self.keywords.is_empty() {
+            return None
+        }
+
+        let lowercase;
+        let word = if self.case_insensitive {
+            lowercase = word.to_lowercase();
+            &lowercase
+        } else {
+            word
+        };
+
+        let (bucket, f1, f2) = hash(word, self.seed);
+        let displacement = self.displacements[(bucket % self.displacements.len() as u64) as usize];
+        let i = self.slots[displace(f1, f2, displacement, self.keywords.len() as u64)];
+        (self.keywords[i] == word).then_some(i)
+    }
+}
+
+fn displace(f1: u64, f2: u64, (d1, d2): (u64, u64), len: u64) -> usize {
+    (f1.wrapping_add(d1.wrapping_mul(f2)).wrapping_add(d2) % len) as usize
+}
+
+/// FNV-1a, split into three independent hashes
+fn hash(word: &str, seed: u64) -> (u64, u64, u64) {
+    let mut hash = 0xcbf29ce484222325 ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+    for byte in word.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    let a = mix(hash);
+    let b = mix(a);
+    (a >> 32, a & 0xffffffff, b & 0xffffffff)
+}
+
+/// The finalizer of splitmix64
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
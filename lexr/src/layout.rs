@@ -0,0 +1,237 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use crate::{LexError, SrcLoc};
+
+/// How tab characters in indentation are treated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabPolicy {
+    /// Tabs advance to the next multiple of the given width, and the resulting widths are compared.
+    Expand(usize),
+    /// Tabs are not allowed in indentation.
+    Forbid,
+    /// Indentation is compared as text. A deeper level must start with the enclosing level,
+    /// so tabs and spaces can be mixed as long as it is done the same way on every line.
+    Strict,
+}
+
+/// Settings for the off-side rule used by a [`LayoutLexer`].
+///
+/// The constructors are used to synthesize the indentation tokens.
+pub struct Layout<T> {
+    indent: fn() -> T,
+    dedent: fn() -> T,
+    newline: fn() -> T,
+    tabs: TabPolicy,
+    ignore: fn(&T) -> bool,
+}
+
+impl<T> Layout<T> {
+    /// Create a new layout from constructors for the INDENT, DEDENT and NEWLINE tokens.
+    ///
+    /// Tabs are handled with [`TabPolicy::Strict`] unless otherwise specified.
+    pub fn new(indent: fn() -> T, dedent: fn() -> T, newline: fn() -> T) -> Self {
+        Self {
+            indent,
+            dedent,
+            newline,
+            tabs: TabPolicy::Strict,
+            ignore: |_| false,
+        }
+    }
+
+    /// Set the policy for tabs in indentation.
+    pub fn tabs(mut self, policy: TabPolicy) -> Self {
+        self.tabs = policy;
+        self
+    }
+
+    /// Tokens for which `ignore` returns true are passed through without affecting the layout.
+    ///
+    /// This is useful if comments are emitted as tokens, instead of being skipped.
+    pub fn ignore(mut self, ignore: fn(&T) -> bool) -> Self {
+        self.ignore = ignore;
+        self
+    }
+}
+
+struct Level {
+    text: String,
+    width: usize,
+}
+
+/// A lexer adapter that applies the off-side rule.
+///
+/// The first token on each line is compared to the indentation of the enclosing block, and
+/// INDENT, DEDENT and NEWLINE tokens are synthesized accordingly.
+/// Lines without tokens, such as blank lines and lines with skipped comments, are ignored.
+///
+/// A zero width token at the very end of the input is treated as end of file,
+/// and all open blocks are closed before it.
+///
+/// Created with [`Lexer::layout`](crate::Lexer::layout).
+pub struct LayoutLexer<'a, T, Ite: Iterator<Item = (T, SrcLoc)>> {
+    src: &'a str,
+    /// The absolute byte index where `src` starts
+    offset: usize,
+    inner: Ite,
+    layout: Layout<T>,
+    levels: Vec<Level>,
+    queue: VecDeque<Result<(T, SrcLoc), LexError>>,
+    last: Option<SrcLoc>,
+    done: bool,
+}
+
+impl<'a, T, Ite: Iterator<Item = (T, SrcLoc)>> LayoutLexer<'a, T, Ite> {
+    pub(crate) fn new(src: &'a str, offset: usize, inner: Ite, layout: Layout<T>) -> Self {
+        Self {
+            src,
+            offset,
+            inner,
+            layout,
+            levels: vec![Level { text: String::new(), width: 0 }],
+            queue: VecDeque::new(),
+            last: None,
+            done: false,
+        }
+    }
+
+    fn process(&mut self, token: T, loc: SrcLoc) {
+        if (self.layout.ignore)(&token) {
+            self.queue.push_back(Ok((token, loc)));
+            return
+        }
+
+        let (start_idx, end_idx) = loc.get_abs_loc();
+        if start_idx == end_idx && start_idx == self.offset + self.src.len() {
+            self.close_blocks(loc);
+            self.queue.push_back(Ok((token, loc)));
+            return
+        }
+
+        if self.last.is_none_or(|last| loc.start().0 > last.end().0) {
+            if self.last.is_some() {
+                self.push_newline();
+            }
+
+            let at = SrcLoc::new(loc.start(), loc.start(), (start_idx, start_idx));
+            if let Err(err) = self.indentation(start_idx, at).and_then(|level| self.enter(level, at)) {
+                self.queue.push_back(Err(err));
+                self.done = true;
+                return
+            }
+        }
+
+        self.queue.push_back(Ok((token, loc)));
+        self.last = Some(loc);
+    }
+
+    /// Finds the leading whitespace of the line containing `idx`.
+    fn indentation(&self, idx: usize, at: SrcLoc) -> Result<Level, LexError> {
+        let idx = idx - self.offset;
+        let line_start = self.src[..idx].rfind('\n').map_or(0, |i| i + 1);
+        let text: String = self.src[line_start..idx].chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+
+        let width = match self.layout.tabs {
+            TabPolicy::Forbid if text.contains('\t') => return Err(LexError::MixedIndentation { loc: at }),
+            TabPolicy::Expand(tab_width) => {
+                let tab_width = tab_width.max(1);
+                text.chars().fold(0, |w, c| if c == '\t' { (w / tab_width + 1) * tab_width } else { w + 1 })
+            },
+            _ => text.chars().count(),
+        };
+
+        Ok(Level { text, width })
+    }
+
+    /// Compares two levels. None if they can not be compared under the tab policy.
+    fn compare(&self, a: &Level, b: &Level) -> Option<Ordering> {
+        match self.layout.tabs {
+            TabPolicy::Strict => {
+                if a.text == b.text {
+                    Some(Ordering::Equal)
+                } else if b.text.starts_with(&a.text) {
+                    Some(Ordering::Less)
+                } else if a.text.starts_with(&b.text) {
+                    Some(Ordering::Greater)
+                } else {
+                    None
+                }
+            },
+            _ => Some(a.width.cmp(&b.width)),
+        }
+    }
+
+    fn enter(&mut self, level: Level, at: SrcLoc) -> Result<(), LexError> {
+        match self.compare(self.levels.last().unwrap(), &level) {
+            None => return Err(LexError::MixedIndentation { loc: at }),
+            Some(Ordering::Less) => {
+                self.levels.push(level);
+                self.queue.push_back(Ok(((self.layout.indent)(), at)));
+            },
+            Some(Ordering::Equal) => (),
+            Some(Ordering::Greater) => loop {
+                self.levels.pop();
+                self.queue.push_back(Ok(((self.layout.dedent)(), at)));
+                match self.compare(self.levels.last().unwrap(), &level) {
+                    Some(Ordering::Equal) => break,
+                    Some(Ordering::Greater) => continue,
+                    _ => return Err(LexError::InconsistentDedent { loc: at }),
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn push_newline(&mut self) {
+        let last = self.last.unwrap();
+        let (line, col) = last.end();
+        let idx = last.get_abs_loc().1;
+        self.queue.push_back(Ok(((self.layout.newline)(), SrcLoc::new((line, col + 1), (line, col + 1), (idx, idx)))));
+    }
+
+    /// Ends the current line and closes all open blocks at `at`.
+    fn close_blocks(&mut self, at: SrcLoc) {
+        if self.last.is_none() {
+            return
+        }
+
+        self.push_newline();
+        while self.levels.len() > 1 {
+            self.levels.pop();
+            self.queue.push_back(Ok(((self.layout.dedent)(), at)));
+        }
+        self.last = None;
+    }
+}
+
+impl<'a, T, Ite: Iterator<Item = (T, SrcLoc)>> Iterator for LayoutLexer<'a, T, Ite> {
+    type Item = Result<(T, SrcLoc), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.queue.pop_front() {
+                return Some(item)
+            }
+
+            if self.done {
+                return None
+            }
+
+            match self.inner.next() {
+                Some((token, loc)) => self.process(token, loc),
+                None => {
+                    if let Some(last) = self.last {
+                        let (line, col) = last.end();
+                        let idx = last.get_abs_loc().1;
+                        self.close_blocks(SrcLoc::new((line, col + 1), (line, col + 1), (idx, idx)));
+                    }
+                    self.done = true;
+                },
+            }
+        }
+    }
+}
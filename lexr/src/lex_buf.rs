@@ -1,4 +1,7 @@
-use std::{rc::Rc, cell::RefCell};
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use core::cell::RefCell;
+use crate::{LexError, SrcLoc};
 
 /// A buffer for lexing.
 /// 
@@ -13,6 +16,16 @@ pub struct LexBuf<'a> {
     pub col: Rc<RefCell<usize>>,
     pub idx: Rc<RefCell<usize>>,
     pub empty: Rc<RefCell<bool>>,
+    /// Whether more source may follow the buffered source, as when lexing a [`LexStream`](crate::LexStream)
+    pub(crate) partial: Rc<RefCell<bool>>,
+    /// Whether lexing stopped to wait for more source, as the buffered source could not decide the next match
+    pub(crate) starved: Rc<RefCell<bool>>,
+    /// The absolute byte index where lexing stops, as when lexing the chunks of a source in `Parallel`
+    pub(crate) limit: Rc<RefCell<usize>>,
+    /// Whether an error stops lexing and is kept in `error`, rather than panicking, as when lexing the chunks of a source in `Parallel`
+    pub(crate) keep_errors: Rc<RefCell<bool>>,
+    /// The error that stopped lexing, when errors are kept
+    pub(crate) error: Rc<RefCell<Option<LexError>>>,
 }
 
 impl<'a> LexBuf<'a> {
@@ -23,10 +36,152 @@ impl<'a> LexBuf<'a> {
             col: self.col.clone(),
             idx: self.idx.clone(),
             empty: self.empty.clone(),
+            partial: self.partial.clone(),
+            starved: self.starved.clone(),
+            limit: self.limit.clone(),
+            keep_errors: self.keep_errors.clone(),
+            error: self.error.clone(),
+        }
+    }
+
+    /// A buffer for `source`, which starts at the line and column `pos`, and the absolute byte index `idx`.
+    pub(crate) fn resumed(source: &'a str, pos: (usize, usize), idx: usize, partial: bool) -> Self {
+        Self {
+            source: Rc::new(RefCell::new(source)),
+            line: Rc::new(RefCell::new(pos.0)),
+            col: Rc::new(RefCell::new(pos.1)),
+            idx: Rc::new(RefCell::new(idx)),
+            empty: Rc::new(RefCell::new(false)),
+            partial: Rc::new(RefCell::new(partial)),
+            starved: Rc::new(RefCell::new(false)),
+            limit: Rc::new(RefCell::new(usize::MAX)),
+            keep_errors: Rc::new(RefCell::new(false)),
+            error: Rc::new(RefCell::new(None)),
         }
     }
 }
 
+impl<'a> LexBuf<'a> {
+    /// The zero width location of the current position in the buffer.
+    pub fn current_loc(&self) -> SrcLoc {
+        let pos = (*self.line.borrow(), *self.col.borrow());
+        let idx = *self.idx.borrow();
+        SrcLoc::new(pos, pos, (idx, idx))
+    }
+
+    /// Whether the buffer is positioned at the beginning of a line.
+    pub fn at_line_start(&self) -> bool {
+        *self.col.borrow() == 1
+    }
+
+    /// Whether lexing stopped to wait for more source, or at the limit of the buffer.
+    pub fn is_starved(&self) -> bool {
+        *self.starved.borrow()
+    }
+
+    #[doc(hidden)]
+    /// Marks the source as a part that more source may follow, as a [`LexStream`](crate::LexStream) does.
+    pub fn set_partial(&self, partial: bool) {
+        self.partial.replace(partial);
+    }
+
+    #[doc(hidden)]
+    /// Stops lexing if the source is a part that can not decide the next match, and returns whether it did.
+    pub fn starve_if_partial(&self) -> bool {
+        let partial = *self.partial.borrow();
+        if partial {
+            self.starved.replace(true);
+        }
+        partial
+    }
+
+    #[doc(hidden)]
+    /// Whether lexing should stop before the rules are tried again.
+    ///
+    /// The empty remainder is tried once, so `eof` can match, unless more source may follow it.
+    /// Reaching the limit starves the buffer, as the source continues in another buffer.
+    pub fn exhausted(&self) -> bool {
+        if *self.empty.borrow() || self.is_starved() || self.error.borrow().is_some() {
+            return true
+        }
+        if *self.idx.borrow() >= *self.limit.borrow() {
+            self.starved.replace(true);
+            return true
+        }
+        if self.source.borrow().is_empty() {
+            if self.starve_if_partial() {
+                return true
+            }
+            self.empty.replace(true);
+        }
+        false
+    }
+
+    #[doc(hidden)]
+    /// Panics with `err`, unless errors are kept, in which case lexing stops with the first of them instead.
+    pub fn fail(&self, err: LexError) {
+        if !*self.keep_errors.borrow() {
+            panic!("{}", err)
+        }
+        self.error.borrow_mut().get_or_insert(err);
+    }
+
+    /// Consumes `length` bytes of the remaining source, and returns the location of the consumed text.
+    pub fn consume(&self, length: usize) -> SrcLoc {
+        let (loc, (line, col)) = self.measure(length);
+        self.line.replace(line);
+        self.col.replace(col);
+        self.idx.replace(loc.get_abs_loc().1);
+
+        let mut src = self.source.borrow_mut();
+        *src = &src[length..];
+        loc
+    }
+
+    /// Scans the remaining source until `terminator` is found, and consumes everything up to and including it.
+    ///
+    /// Returns the text before the terminator, and the location of all the consumed text.
+    /// If the terminator is never found, nothing is consumed and an error is returned.
+    pub fn scan_until(&self, terminator: impl AsRef<str>) -> Result<(&'a str, SrcLoc), LexError> {
+        self.consume_delimited(0, terminator)
+    }
+
+    #[doc(hidden)]
+    /// Like `scan_until`, but the first `open_len` bytes are the opening delimiter, which is consumed as well.
+    pub fn consume_delimited(&self, open_len: usize, terminator: impl AsRef<str>) -> Result<(&'a str, SrcLoc), LexError> {
+        let terminator = terminator.as_ref();
+        let src: &'a str = *self.source.borrow();
+
+        match src[open_len..].find(terminator) {
+            Some(pos) => {
+                let body = &src[open_len..open_len + pos];
+                Ok((body, self.consume(open_len + pos + terminator.len())))
+            },
+            None => {
+                // The terminator may be in the source that follows
+                self.starve_if_partial();
+                Err(LexError::Unterminated {
+                    terminator: terminator.to_string(),
+                    loc: self.measure(open_len).0,
+                })
+            }
+        }
+    }
+
+    /// Finds the location of the next `length` bytes, and the position following them.
+    fn measure(&self, length: usize) -> (SrcLoc, (usize, usize)) {
+        let start = (*self.line.borrow(), *self.col.borrow());
+        let (mut end, mut next) = (start, start);
+        for c in self.source.borrow()[..length].chars() {
+            end = next;
+            next = if c == '\n' { (next.0 + 1, 1) } else { (next.0, next.1 + 1) };
+        }
+
+        let idx = *self.idx.borrow();
+        (SrcLoc::new(start, end, (idx, idx + length)), next)
+    }
+}
+
 impl<'a> LexBuf<'a> {
     pub fn new(source: impl Into<LexBuf<'a>>) -> Self {
         source.into()
@@ -35,24 +190,12 @@ impl<'a> LexBuf<'a> {
 
 impl<'a> From<&'a str> for LexBuf<'a> {
     fn from(value: &'a str) -> Self {
-        Self {
-            source: Rc::new(RefCell::new(value)),
-            line: Rc::new(RefCell::new(1)),
-            col: Rc::new(RefCell::new(1)),
-            idx: Rc::new(RefCell::new(0)),
-            empty: Rc::new(RefCell::new(false)),
-        }
+        Self::resumed(value, (1, 1), 0, false)
     }
 }
 
 impl<'a> From<&'a String> for LexBuf<'a> {
     fn from(source: &'a String) -> Self {
-        Self {
-            source: Rc::new(RefCell::new(source)),
-            line: Rc::new(RefCell::new(1)),
-            col: Rc::new(RefCell::new(1)),
-            idx: Rc::new(RefCell::new(0)),
-            empty: Rc::new(RefCell::new(false)),
-        }
+        Self::resumed(source, (1, 1), 0, false)
     }
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use alloc::string::String;
+use core::fmt::Display;
+use crate::SrcLoc;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An error encountered while lexing
+pub enum LexError {
+    /// No rule matched the input at this location
+    UnexpectedChar { c: char, loc: SrcLoc },
+    /// The terminator of a delimited literal was never found
+    Unterminated { terminator: String, loc: SrcLoc },
+    /// A dedent did not return to any enclosing indentation level
+    InconsistentDedent { loc: SrcLoc },
+    /// The indentation mixes tabs and spaces in a way the [`TabPolicy`](crate::layout::TabPolicy) does not allow
+    MixedIndentation { loc: SrcLoc },
+}
+
+impl LexError {
+    /// The location of the error in the source
+    pub fn loc(&self) -> SrcLoc {
+        match self {
+            LexError::UnexpectedChar { loc, .. } => *loc,
+            LexError::Unterminated { loc, .. } => *loc,
+            LexError::InconsistentDedent { loc } => *loc,
+            LexError::MixedIndentation { loc } => *loc,
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LexError::UnexpectedChar { c, loc } => write!(f, "Unexpected character '{}' at {}", c, loc),
+            LexError::Unterminated { terminator, loc } => write!(f, "Unterminated literal starting at {}, expected '{}'", loc, terminator),
+            LexError::InconsistentDedent { loc } => write!(f, "Dedent does not match any outer indentation level at {}", loc),
+            LexError::MixedIndentation { loc } => write!(f, "Inconsistent use of tabs and spaces in indentation at {}", loc),
+        }
+    }
+}
+
+impl core::error::Error for LexError {}
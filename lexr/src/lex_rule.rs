@@ -1,7 +1,13 @@
 #[doc(hidden)]
 pub use concat_idents::concat_idents;
+#[cfg(feature = "std")]
 pub use lazy_static;
+#[cfg(feature = "std")]
 pub use regex;
+#[doc(hidden)]
+pub use alloc::string::String as __String;
+#[doc(hidden)]
+pub use crate::sync::Lazy;
 
 #[macro_export]
 /// Define a lexer function with provided rules.
@@ -42,10 +48,173 @@ pub use regex;
 ///     ]);
 ///
 macro_rules! lex_rule {
-    ($v:vis $name:ident $(<$($lt:lifetime),+>)? $(($($arg:ident: $arg_typ:ty),*))? -> $token:ty {
-        $($regpat:tt $($regex:expr)* => |$id:pat_param $(,$src_id:pat_param $(,$loc_id:pat_param)?)?| $closure:expr),* $(,)?
+    ($(#[$flag:ident])* $v:vis $name:ident $(<$($lt:lifetime),+>)? $(($($arg:ident: $arg_typ:ty),*))? -> $token:ty $(, $prev:ident: $prev_typ:ty = $summary:path)? {
+        $($rules:tt)*
     }) => {
-    lexr::concat_idents!(name = _LEXER_, $name {
+        $(lexr::lex_rule!{@flag $flag})*
+        lexr::lex_rule!{@rules [[$($flag)*] $v $name $(<$($lt),+>)? $(($($arg: $arg_typ),*))? -> $token $(, $prev: $prev_typ = $summary)?] [] $($rules)*}
+    };
+
+    // Pattern flags, which can be given for the whole lexer or a single rule
+    (@flag case_insensitive) => {};
+    (@flag ascii_classes) => {};
+    (@flag dot_all) => {};
+    (@flag $flag:ident) => {
+        compile_error!(concat!("Unknown pattern flag `", stringify!($flag), "`"));
+    };
+
+    // The rules are parsed one at a time into the form
+    // { [OPTIONS] [PATTERN] [TRAIL] [UNTIL] [GUARD] [STR_ID] [BUF_ID] [LOC_ID] [CAPS_ID] [ACTION] }
+    (@rules $header:tt [$($done:tt)*] $(,)?) => {
+        lexr::lex_rule!{@gen $header $($done)*}
+    };
+
+    (@rules $header:tt $done:tt , $($rest:tt)*) => {
+        lexr::lex_rule!{@rules $header $done $($rest)*}
+    };
+
+    // Rule sets expand their rules in place, and pass the rest back
+    (@rules $header:tt $done:tt include $($set:ident)::+ $(, $($rest:tt)*)?) => {
+        $($set)::+!{@include $header $done $($($rest)*)?}
+    };
+
+    // Single token patterns are by far the most common, so they are parsed in one step
+    (@rules $header:tt [$($done:tt)*] $regpat:tt => |$id:pat_param $(,$src_id:pat_param $(,$loc_id:pat_param $(,$caps_id:pat_param)?)?)?| $closure:expr $(, $($rest:tt)*)?) => {
+        lexr::lex_rule!{@rules $header [$($done)* {
+            [] [$regpat] [] [] [] [$id] [$($src_id)?] [$($($loc_id)?)?] [$($($($caps_id)?)?)?] [$closure]
+        }] $($($rest)*)?}
+    };
+
+    (@rules $header:tt $done:tt $($rest:tt)+) => {
+        lexr::lex_rule!{@pre $header $done [] $($rest)+}
+    };
+
+    // Options preceding the pattern
+    (@pre $header:tt $done:tt [$($pre:tt)*] ^ $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* bol] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[priority($p:expr)] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* priority($p)] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[case_insensitive] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* case_insensitive] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[ascii_classes] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* ascii_classes] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[dot_all] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* dot_all] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[name($rule:ident)] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* name($rule)] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[before($rule:ident)] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* before($rule)] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt [$($pre:tt)*] #[after($rule:ident)] $($rest:tt)*) => {
+        lexr::lex_rule!{@pre $header $done [$($pre)* after($rule)] $($rest)*}
+    };
+
+    (@pre $header:tt $done:tt $pre:tt #[$($attr:tt)*] $($rest:tt)*) => {
+        compile_error!(concat!("Unknown rule attribute `", stringify!($($attr)*), "`"));
+    };
+
+    (@pre $header:tt $done:tt $pre:tt $($rest:tt)*) => {
+        lexr::lex_rule!{@pat $header $done $pre [] $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)+] => $($rest:tt)*) => {
+        lexr::lex_rule!{@action $header $done [$pre [$($regpat)+] [] [] []] => $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)+] if $($rest:tt)*) => {
+        lexr::lex_rule!{@guard $header $done [$pre [$($regpat)+] [] []] [] $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)+] / $($rest:tt)*) => {
+        lexr::lex_rule!{@trail $header $done [$pre [$($regpat)+]] [] $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)+] until |$open:pat_param| $($rest:tt)*) => {
+        lexr::lex_rule!{@until $header $done [$pre [$($regpat)+] []] [$open] [] $($rest)*}
+    };
+
+    // Keywords are kept with the options
+    (@pat $header:tt $done:tt [$($pre:tt)*] [$($regpat:tt)+] keywords {$($kw:tt)*} $($rest:tt)*) => {
+        lexr::lex_rule!{@pat $header $done [$($pre)* keywords {$($kw)*}] [$($regpat)+] $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)*] $next:tt $($rest:tt)*) => {
+        lexr::lex_rule!{@pat $header $done $pre [$($regpat)* $next] $($rest)*}
+    };
+
+    (@pat $header:tt $done:tt $pre:tt [$($regpat:tt)*]) => {
+        compile_error!("Expected `=>` after pattern");
+    };
+
+    // Trailing context, which must match after the pattern, but is not consumed
+    (@trail $header:tt $done:tt [$($spec:tt)*] [$($trail:tt)+] => $($rest:tt)*) => {
+        lexr::lex_rule!{@action $header $done [$($spec)* [$($trail)+] [] []] => $($rest)*}
+    };
+
+    (@trail $header:tt $done:tt [$($spec:tt)*] [$($trail:tt)+] if $($rest:tt)*) => {
+        lexr::lex_rule!{@guard $header $done [$($spec)* [$($trail)+] []] [] $($rest)*}
+    };
+
+    (@trail $header:tt $done:tt [$($spec:tt)*] [$($trail:tt)+] until |$open:pat_param| $($rest:tt)*) => {
+        lexr::lex_rule!{@until $header $done [$($spec)* [$($trail)+]] [$open] [] $($rest)*}
+    };
+
+    (@trail $header:tt $done:tt [[$($pre:tt)*] $($spec:tt)*] [$($trail:tt)+] keywords {$($kw:tt)*} $($rest:tt)*) => {
+        lexr::lex_rule!{@trail $header $done [[$($pre)* keywords {$($kw)*}] $($spec)*] [$($trail)+] $($rest)*}
+    };
+
+    (@trail $header:tt $done:tt $spec:tt [$($trail:tt)*] $next:tt $($rest:tt)*) => {
+        lexr::lex_rule!{@trail $header $done $spec [$($trail)* $next] $($rest)*}
+    };
+
+    (@until $header:tt $done:tt [$($spec:tt)*] [$open:pat_param] [$($term:tt)+] => $($rest:tt)*) => {
+        lexr::lex_rule!{@action $header $done [$($spec)* [$open, $($term)+] []] => $($rest)*}
+    };
+
+    (@until $header:tt $done:tt [$($spec:tt)*] [$open:pat_param] [$($term:tt)+] if $($rest:tt)*) => {
+        lexr::lex_rule!{@guard $header $done [$($spec)* [$open, $($term)+]] [] $($rest)*}
+    };
+
+    (@until $header:tt $done:tt $spec:tt $open:tt [$($term:tt)*] $next:tt $($rest:tt)*) => {
+        lexr::lex_rule!{@until $header $done $spec $open [$($term)* $next] $($rest)*}
+    };
+
+    // A guard that must hold for the rule to be tried
+    (@guard $header:tt $done:tt [$($spec:tt)*] [$($guard:tt)+] => $($rest:tt)*) => {
+        lexr::lex_rule!{@action $header $done [$($spec)* [$($guard)+]] => $($rest)*}
+    };
+
+    (@guard $header:tt $done:tt $spec:tt [$($guard:tt)*] $next:tt $($rest:tt)*) => {
+        lexr::lex_rule!{@guard $header $done $spec [$($guard)* $next] $($rest)*}
+    };
+
+    (@action $header:tt [$($done:tt)*] [$($spec:tt)*] => |$id:pat_param $(,$src_id:pat_param $(,$loc_id:pat_param $(,$caps_id:pat_param)?)?)?| $closure:expr $(, $($rest:tt)*)?) => {
+        lexr::lex_rule!{@rules $header [$($done)* {
+            $($spec)* [$id] [$($src_id)?] [$($($loc_id)?)?] [$($($($caps_id)?)?)?] [$closure]
+        }] $($($rest)*)?}
+    };
+
+    (@action $header:tt $done:tt $spec:tt => $($rest:tt)*) => {
+        compile_error!("Expected an action of the form `|s| ...` after `=>`");
+    };
+
+    (@gen [$flags:tt $v:vis $name:ident $(<$($lt:lifetime),+>)? $(($($arg:ident: $arg_typ:ty),*))? -> $token:ty $(, $prev:ident: $prev_typ:ty = $summary:path)?]
+        $({ [$($pre:tt)*] [$($regpat:tt)+] [$($trail:tt)*] [$($until:tt)*] [$($guard:tt)*] [$id:pat_param] [$($src_id:pat_param)?] [$($loc_id:pat_param)?] [$($caps_id:pat_param)?] [$closure:expr] })*
+    ) => {
+    lexr::concat_idents!(__LexrLexer = _LEXER_, $name {
         #[allow(non_camel_case_types)]
         #[doc(hidden)]
         /// Automatically generated lexer struct. Do not access its fields directly!
@@ -53,78 +222,157 @@ macro_rules! lex_rule {
         /// The `tokens` method returns an iterator over the tokens, stripping away the source locations.
         /// 
         /// `vec` and `token_vec` methods are provided for convenience.
-        $v struct name<'_buf, $($($lt),+)?> {
+        $v struct __LexrLexer<'_buf, $($($lt),+)?> {
             buf: lexr::LexBuf<'_buf>,
-            $($($arg: $arg_typ),*)?
+            __stats: lexr::StatsRecorder,
+            __step: lexr::SrcLoc,
+            $($($arg: $arg_typ,)*)?
+            $($prev: Option<$prev_typ>,)?
         }
 
-        impl<'_buf $(,$($lt),+)?> From<name<'_buf, $($($lt),+)?>> for lexr::Lexer<$token, name<'_buf $(,$($lt),+)?>> {
-            fn from(lexer: name<'_buf $(,$($lt),+)?>) -> Self {
+        impl<'_buf $(,$($lt),+)?> From<__LexrLexer<'_buf, $($($lt),+)?>> for lexr::Lexer<$token, __LexrLexer<'_buf $(,$($lt),+)?>> {
+            fn from(lexer: __LexrLexer<'_buf $(,$($lt),+)?>) -> Self {
                 lexr::Lexer::new(lexer)
             }
         }
 
-        impl<'_src, $($($lt),+)?> Iterator for name<'_src, $($($lt),+)?> {
+        impl<'_src, $($($lt),+)?> Iterator for __LexrLexer<'_src, $($($lt),+)?> {
             type Item = ($token, lexr::SrcLoc);
 
-            #[allow(unreachable_code)]
             fn next(&mut self) -> Option<Self::Item> {
-                $($(let $arg: $arg_typ = self.$arg);*)?;
+                let next = self.lex_next();
+                // A token produced while starved is dropped by the stream, so it is not the previous token
+                $(if let Some((token, _)) = next.as_ref().filter(|_| !self.buf.is_starved()) {
+                    self.$prev = Some($summary(token));
+                })?
+                next
+            }
+        }
+
+        impl<'_src, $($($lt),+)?> lexr::Buffered<'_src> for __LexrLexer<'_src, $($($lt),+)?> {
+            fn buf(&self) -> &lexr::LexBuf<'_src> {
+                &self.buf
+            }
+        }
+
+        impl<'_src, $($($lt),+)?> lexr::Resume for __LexrLexer<'_src, $($($lt),+)?> {
+            type Token = $token;
 
-                let start_idx = *self.buf.idx.borrow();
+            fn resume(&mut self, buf: lexr::LexBuf<'_>, lex: &mut dyn FnMut(&mut lexr::Resumed<'_, $token>)) -> lexr::SrcLoc {
+                let mut lexer = __LexrLexer {
+                    __step: buf.current_loc(),
+                    buf,
+                    __stats: ::core::mem::take(&mut self.__stats),
+                    $($($arg: self.$arg,)*)?
+                    $($prev: self.$prev.take(),)?
+                };
+                lex(&mut lexer);
+                self.__stats = lexer.__stats;
+                $(self.$prev = lexer.$prev;)?
+                lexer.__step
+            }
+
+            fn keeps_state(&self) -> bool {
+                lexr::lex_rule!(@is_set [$($prev)?])
+            }
+        }
 
+        impl<'_src, $($($lt),+)?> lexr::Rules for __LexrLexer<'_src, $($($lt),+)?> {
+            fn rule_table() -> &'static lexr::RuleTable {
+                static RULES: lexr::Lazy<lexr::RuleTable> = lexr::Lazy::new(|| lexr::RuleTable::new([$(
+                    lexr::RuleSpec {
+                        source: stringify!($($regpat)+).into(),
+                        name: lexr::lex_rule!(@name [$($pre)*]),
+                        placement: lexr::lex_rule!(@placement [$($pre)*]),
+                        pattern: lexr::lex_rule!(@pattern $($regpat)+),
+                        trail: lexr::lex_rule!(@concat [$($trail)*]),
+                        priority: lexr::lex_rule!(@priority [$($pre)*]),
+                        bol: lexr::lex_rule!(@has_opt bol [$($pre)*]),
+                        case_insensitive: lexr::lex_rule!(@has_opt case_insensitive [$($pre)*]) || lexr::lex_rule!(@has_opt case_insensitive $flags),
+                        ascii_classes: lexr::lex_rule!(@has_opt ascii_classes [$($pre)*]) || lexr::lex_rule!(@has_opt ascii_classes $flags),
+                        dot_all: lexr::lex_rule!(@has_opt dot_all [$($pre)*]) || lexr::lex_rule!(@has_opt dot_all $flags),
+                        guarded: lexr::lex_rule!(@is_set [$($guard)*]),
+                        captures: lexr::lex_rule!(@is_set [$($caps_id)?]),
+                        delimited: lexr::lex_rule!(@is_set [$($until)*]),
+                        keywords: lexr::lex_rule!(@keyword_words [$($pre)*]),
+                    }
+                ),*].into()));
+                &RULES
+            }
+
+            fn stats(&self) -> &lexr::StatsRecorder {
+                &self.__stats
+            }
+        }
+
+        impl<'_src, $($($lt),+)?> __LexrLexer<'_src, $($($lt),+)?> {
+            #[allow(unreachable_code, clippy::diverging_sub_expression)]
+            fn lex_next(&mut self) -> Option<($token, lexr::SrcLoc)> {
+                $($(let $arg: $arg_typ = self.$arg);*)?;
+                $(#[allow(unused_variables)] let $prev: Option<$prev_typ> = self.$prev.clone();)?
+
+                let rules = <Self as lexr::Rules>::rule_table();
+                // The rule whose action is running, to trace if it continues or breaks
+                let mut _running: Option<(usize, lexr::SrcLoc)> = None;
                 let mut matched = false;
                 loop {
-                    // These allow for seamless matching of eof
+                    if let Some((rule, loc)) = _running.take() {
+                        rules.outcome(rule, loc, lexr::Outcome::Continue);
+                    }
+
+                    // This allows for seamless matching of eof
                     matched = false;
-                    let mut src = self.buf.source.borrow_mut();
-                    if *self.buf.empty.borrow() { break }
-                    if src.len() == 0 { *self.buf.empty.borrow_mut() = true; }
-                    
-                    $(
-                    let regex = lex_rule!(@regex_rule $regpat $($regex)*);
-                    if let Some(mat) = regex.find(&src) {
-                        matched = true;
-                        let length = mat.end();
-                        
-                        let start = (*self.buf.line.borrow(), *self.buf.col.borrow());
-                        let mut end = start;
-                        
-                        let mut source_iter = src.chars();
-                        for i in 0..length {
-                            let c = source_iter.next().unwrap();
-                            if i == length - 1 {
-                                end = (*self.buf.line.borrow(), *self.buf.col.borrow());
-                            }
-                            if c == '\n' {
-                                *self.buf.line.borrow_mut() += 1;
-                                *self.buf.col.borrow_mut() = 1;
-                            } else {
-                                *self.buf.col.borrow_mut() += 1;
-                            }
-                        }
+                    // Sub rules starving in an action leave the source half consumed, so a stream continues from the start of the step
+                    if !self.buf.is_starved() { self.__step = self.buf.current_loc(); }
+                    if self.buf.exhausted() { break }
 
-                        *src = &src[length..];
-                        let end_idx = start_idx + length;
-                        self.buf.idx.replace(end_idx);
+                    let found = rules.select(&self.buf, &self.__stats, |_rule| {
+                        #[allow(unused_mut)]
+                        let mut _k = 0usize;
+                        $(
+                        if _k == _rule { return lexr::lex_rule!(@guard_holds [$($guard)*]) }
+                        _k += 1;
+                        )*
+                        true
+                    });
 
-                        let $id = mat.as_str();
-                        $($(let $loc_id = lexr::SrcLoc::new(start, end, (start_idx, end_idx));)?)?
-                        drop(src);
-                        let token = {
-                            $(let $src_id = self.buf.share();)?
-                            $closure
-                        };
+                    // The selected rule is found by counting, as the actions must be expanded in this loop
+                    if let Some(lexr::RuleMatch { rule: _rule, lexeme: mat, caps: _caps }) = found {
+                        matched = true;
+                        #[allow(unused_mut)]
+                        let mut _k = 0usize;
+                        $(
+                        if _k == _rule {
+                            let (lexeme, loc) = lexr::lex_rule!(@consume self.buf, mat, [$($until)*]);
+                            self.__stats.selected(_rule, loc.get_abs_loc());
+                            lexr::lex_rule!{@keyword_lookup [$($pre)*] rules, _rule, lexeme, loc}
 
-                        return Some((token, lexr::SrcLoc::new(start, end, (start_idx, end_idx))));
-                    })*
+                            let $id = lexeme;
+                            $(let $loc_id = loc;)?
+                            $(let $caps_id = _caps.unwrap();)?
+                            _running = Some((_rule, loc));
+                            let token = {
+                                $(let $src_id = self.buf.share();)?
+                                $closure
+                            };
+
+                            rules.outcome(_rule, loc, lexr::Outcome::Token);
+                            return Some((token, loc));
+                        }
+                        _k += 1;
+                        )*
+                    }
 
                     break
                 }
 
-                if !*self.buf.empty.borrow() && !matched {
+                if let Some((rule, loc)) = _running {
+                    rules.outcome(rule, loc, lexr::Outcome::Break);
+                }
+
+                if !*self.buf.empty.borrow() && !self.buf.is_starved() && !matched {
                     if let Some(c) = self.buf.source.borrow().chars().next() {
-                        panic!("Unexpected character '{}' at {}", c, lexr::SrcLoc::new((*self.buf.line.borrow(), *self.buf.col.borrow()), (*self.buf.line.borrow(), *self.buf.col.borrow()), (*self.buf.idx.borrow(), *self.buf.idx.borrow())));
+                        self.buf.fail(lexr::LexError::UnexpectedChar { c, loc: self.buf.current_loc() });
                     }
                 }
 
@@ -137,43 +385,158 @@ macro_rules! lex_rule {
         /// Creates a new lexer from a string slice.
         /// 
         /// A [`Lexer`](crate::Lexer) is returned, which can be used to iterate over the tokens.
-        $v fn $name<'_buf $(,$($lt),+)?>(buf: impl Into<lexr::LexBuf<'_buf>> $(,$($arg: $arg_typ),*)?) -> lexr::Lexer<$token, name<'_buf $(,$($lt),+)?>> {
-            lexr::Lexer::new(name {
+        $v fn $name<'_buf $(,$($lt),+)?>(buf: impl Into<lexr::LexBuf<'_buf>> $(,$($arg: $arg_typ),*)?) -> lexr::Lexer<$token, __LexrLexer<'_buf $(,$($lt),+)?>> {
+            // Builds the rules, so invalid patterns are reported when the lexer is created
+            <__LexrLexer<'_buf $(,$($lt),+)?> as lexr::Rules>::rule_table();
+            lexr::Lexer::new(__LexrLexer {
                 buf: buf.into(),
-                $($($arg),*)?
+                __stats: Default::default(),
+                __step: lexr::SrcLoc::DUMMY,
+                $($($arg,)*)?
+                $($prev: None,)?
             })
         }
     });};
 
-    (@regex_rule _) => {{
-        lexr::lazy_static::lazy_static! {
-            static ref REGEX: lexr::regex::Regex = lexr::regex::Regex::new(r"(?s)^.").unwrap();
-        }; 
-        &REGEX
-    }};
+    // Finds the options of a rule
+    (@has_opt $opt:ident []) => { false };
+    (@has_opt bol [bol $($rest:tt)*]) => { true };
+    (@has_opt case_insensitive [case_insensitive $($rest:tt)*]) => { true };
+    (@has_opt ascii_classes [ascii_classes $($rest:tt)*]) => { true };
+    (@has_opt dot_all [dot_all $($rest:tt)*]) => { true };
+    (@has_opt $opt:ident [$other:tt $($rest:tt)*]) => { lexr::lex_rule!(@has_opt $opt [$($rest)*]) };
+
+    (@priority []) => { 0 };
+    (@priority [priority($p:expr) $($rest:tt)*]) => { $p };
+    (@priority [$other:tt $($rest:tt)*]) => { lexr::lex_rule!(@priority [$($rest)*]) };
+
+    (@name []) => { None };
+    (@name [name($rule:ident) $($rest:tt)*]) => { Some(stringify!($rule)) };
+    (@name [$other:tt $($rest:tt)*]) => { lexr::lex_rule!(@name [$($rest)*]) };
+
+    (@placement []) => { lexr::Placement::Declared };
+    (@placement [before($rule:ident) $($rest:tt)*]) => { lexr::Placement::Before(stringify!($rule)) };
+    (@placement [after($rule:ident) $($rest:tt)*]) => { lexr::Placement::After(stringify!($rule)) };
+    (@placement [$other:tt $($rest:tt)*]) => { lexr::lex_rule!(@placement [$($rest)*]) };
+
+    (@keyword_words []) => { &[] };
+    (@keyword_words [keywords {$($word:literal => $kw:expr),* $(,)?} $($rest:tt)*]) => { &[$($word),*] };
+    (@keyword_words [$other:tt $($rest:tt)*]) => { lexr::lex_rule!(@keyword_words [$($rest)*]) };
+
+    // Returns the keyword token if the lexeme is a keyword
+    (@keyword_lookup [] $rules:expr, $rule:expr, $lexeme:expr, $loc:expr) => {};
+    (@keyword_lookup [keywords {$($word:literal => $kw:expr),* $(,)?} $($rest:tt)*] $rules:expr, $rule:expr, $lexeme:expr, $loc:expr) => {
+        if let Some(keyword) = $rules.keyword($rule, $lexeme) {
+            let mut _k = 0usize;
+            let mut token = None;
+            $(
+            if _k == keyword { token = Some($kw) }
+            _k += 1;
+            )*
+            $rules.outcome($rule, $loc, lexr::Outcome::Keyword);
+            return Some((token.unwrap(), $loc));
+        }
+    };
+    (@keyword_lookup [$other:tt $($rest:tt)*] $rules:expr, $rule:expr, $lexeme:expr, $loc:expr) => {
+        lexr::lex_rule!{@keyword_lookup [$($rest)*] $rules, $rule, $lexeme, $loc}
+    };
+
+    (@is_set []) => { false };
+    (@is_set [$($tt:tt)+]) => { true };
+
+    (@guard_holds []) => { true };
+    (@guard_holds [$($guard:tt)+]) => { ($($guard)+) };
 
-    (@regex_rule eof) => {{
-        lexr::lazy_static::lazy_static!{
-            static ref REGEX: lexr::regex::Regex = lexr::regex::Regex::new(r"^\z").unwrap();
-        }; 
-        &REGEX
+    // Consumes the match, and returns the lexeme passed to the action along with its location
+    (@consume $buf:expr, $mat:expr, []) => {{
+        let lexeme = $mat;
+        (lexeme, $buf.consume(lexeme.len()))
     }};
 
-    (@regex_rule ws) => {{
-        lexr::lazy_static::lazy_static!{
-            static ref REGEX: lexr::regex::Regex = lexr::regex::Regex::new(r"^[ \n\r\t]").unwrap();
-        }; 
-        &REGEX
+    (@consume $buf:expr, $mat:expr, [$open:pat_param, $($term:tt)+]) => {{
+        let open = $mat;
+        let terminator = {
+            let $open = open;
+            $($term)+
+        };
+        match $buf.consume_delimited(open.len(), terminator) {
+            Ok(res) => res,
+            // The terminator may follow in more source
+            Err(_) if $buf.is_starved() => break,
+            Err(err) => {
+                $buf.fail(err);
+                break
+            }
+        }
     }};
 
-    (@regex_rule $($regex:expr)+) => {{
-        lexr::lazy_static::lazy_static!{
-            static ref REGEX: lexr::regex::Regex = lexr::regex::Regex::new({
-                let mut r_str = "^".to_string();
-                $(r_str.push_str($regex);)+
-                r_str
-            }.as_str()).unwrap();
-        }; 
-        &REGEX
+    (@pattern _) => { lexr::Pattern::Builtin(lexr::patterns::ANY) };
+    (@pattern eof) => { lexr::Pattern::Builtin(lexr::patterns::EOF) };
+    (@pattern ws) => { lexr::Pattern::Builtin(lexr::patterns::WS) };
+    (@pattern newline) => { lexr::Pattern::Builtin(lexr::patterns::NEWLINE) };
+    (@pattern xid_ident) => { lexr::Pattern::Builtin(lexr::patterns::XID_IDENT) };
+    (@pattern ascii_ident) => { lexr::Pattern::Builtin(lexr::patterns::ASCII_IDENT) };
+    (@pattern int) => { lexr::Pattern::Builtin(lexr::patterns::INT) };
+    (@pattern float) => { lexr::Pattern::Builtin(lexr::patterns::FLOAT) };
+    (@pattern string) => { lexr::Pattern::Builtin(lexr::patterns::STRING) };
+    (@pattern $($regex:expr)+) => { lexr::Pattern::Regex(lexr::lex_rule!(@concat [$($regex)+]).unwrap()) };
+
+    (@concat []) => { None::<lexr::__String> };
+    (@concat [$($regex:expr)+]) => {{
+        let mut r_str = lexr::__String::new();
+        $(r_str.push_str($regex);)+
+        Some(r_str)
     }};
-}
\ No newline at end of file
+}
+
+#[macro_export]
+/// Define a named set of rules, which can be included in several lexers with `include NAME`.
+///
+/// The set is a macro, so it can be used after its definition in the same module and its children,
+/// or anywhere in the crate if it is declared `pub(crate)`.
+///
+/// More documentation can be found in the [crate root](crate).
+///
+/// # Examples
+///
+///     use lexr::{lex_rule, lex_rule_set};
+///
+///     #[derive(PartialEq, Debug)]
+///     pub enum Token {
+///         Word,
+///         Number,
+///     }
+///
+///     lex_rule_set!{common {
+///         ws => |_| continue,
+///         "[0-9]+" => |_| Token::Number,
+///     }}
+///
+///     lex_rule!{lex -> Token {
+///         include common,
+///         "[a-z]+" => |_| Token::Word,
+///     }}
+///
+///     assert_eq!(lex("abc 123").into_token_vec(), vec![Token::Word, Token::Number]);
+///
+macro_rules! lex_rule_set {
+    (pub($($vis:tt)+) $name:ident { $($rules:tt)* }) => {
+        lexr::lex_rule_set!{@def ($) $name { $($rules)* }}
+        #[allow(unused_imports)]
+        pub($($vis)+) use $name;
+    };
+
+    ($name:ident { $($rules:tt)* }) => {
+        lexr::lex_rule_set!{@def ($) $name { $($rules)* }}
+    };
+
+    // The dollar sign is passed in, so the generated macro can have its own metavariables
+    (@def ($d:tt) $name:ident { $($rules:tt)* }) => {
+        #[allow(unused_macros)]
+        macro_rules! $name {
+            (@include $d header:tt $d done:tt $d($d rest:tt)*) => {
+                lexr::lex_rule!{@rules $d header $d done $($rules)* , $d($d rest)*}
+            };
+        }
+    };
+}
@@ -1,5 +1,10 @@
-use std::iter::Map;
-use crate::SrcLoc;
+use alloc::vec::Vec;
+use core::iter::Map;
+use crate::{SrcLoc, Buffered, RuleInfo, Rules, UnreachableRule, layout::{Layout, LayoutLexer}};
+#[cfg(feature = "std")]
+use crate::{TextMate, TextMateGrammar, textmate::ExportMode};
+#[cfg(feature = "stats")]
+use crate::{RuleStats, Stats};
 
 /// A lexer produces tokens and locations from a defined lexing rule.
 /// 
@@ -7,7 +12,7 @@ use crate::SrcLoc;
 /// 
 /// If it is unable to match any of the patterns, it will panic and report the unexpected character.
 pub struct Lexer<T, Ite: Iterator<Item = (T, crate::SrcLoc)>> {
-    iter: Ite,
+    pub(crate) iter: Ite,
 }
 
 impl<T, Ite: Iterator<Item = (T, crate::SrcLoc)>> Lexer<T, Ite> {
@@ -50,4 +55,54 @@ impl<T, Ite: Iterator<Item = (T, crate::SrcLoc)>> Lexer<T, Ite> {
     pub fn next_token(&mut self) -> Option<T> {
         self.next().map(|a| a.0)
     }
+}
+
+impl<'a, T, Ite: Iterator<Item = (T, crate::SrcLoc)> + Buffered<'a>> Lexer<T, Ite> {
+    /// Applies the off-side rule to the tokens, synthesizing INDENT, DEDENT and NEWLINE tokens.
+    ///
+    /// The layout starts where the lexer is, which should be the start of a line. See [`LayoutLexer`] for details.
+    pub fn layout(self, layout: Layout<T>) -> LayoutLexer<'a, T, Self> {
+        let buf = self.iter.buf();
+        let (src, offset) = (*buf.source.borrow(), *buf.idx.borrow());
+        LayoutLexer::new(src, offset, self, layout)
+    }
+}
+
+impl<T, Ite: Iterator<Item = (T, crate::SrcLoc)> + Rules> Lexer<T, Ite> {
+    /// Information about the rules of the lexer, in the order they are tried.
+    ///
+    /// Rules replaced by a later rule with the same name are not included.
+    pub fn rules(&self) -> Vec<RuleInfo> {
+        Ite::rule_table().rules()
+    }
+
+    /// Finds the rules that can never be selected, as the rules tried before them match every input they do.
+    ///
+    /// Each is reported with the shortest input it matches, and the rule selected for that input instead.
+    /// Rules with guards are assumed to never hold, and rules too complex to analyze are assumed to be reachable.
+    pub fn unreachable_rules(&self) -> Vec<UnreachableRule> {
+        Ite::rule_table().unreachable_rules()
+    }
+
+    #[cfg(feature = "std")]
+    /// Exports the rules as a TextMate grammar, highlighted with the scopes of `textmate`.
+    ///
+    /// The actions are not part of the export, so sub rules called from them are not included.
+    pub fn textmate(&self, textmate: &TextMate) -> TextMateGrammar {
+        textmate.export(&[ExportMode { name: crate::LexerBuilder::<T>::INITIAL, table: Ite::rule_table(), transitions: Vec::new() }])
+    }
+
+    #[cfg(feature = "stats")]
+    /// The statistics of each rule so far, in the order they are tried.
+    ///
+    /// Requires the `stats` feature.
+    pub fn stats(&self) -> Stats {
+        let recorder = self.iter.stats();
+        Stats {
+            rules: self.rules().into_iter().map(|rule| {
+                let counters = recorder.counters(rule.index);
+                RuleStats { rule, counters }
+            }).collect(),
+        }
+    }
 }
\ No newline at end of file
@@ -31,30 +31,42 @@ assert_eq!(tokens, vec![A, B, C])
 The `lex_rule!` macro is used to define a lexer.
 The lex rule has a name, a token type, and any number of patterns with associated actions. The syntax is as follows:
 ```ignore
-lex_rule!{NAME(ARGS) -> TOKEN {
+lex_rule!{FLAGS NAME(ARGS) -> TOKEN, PREV {
     PATTERN => ACTION,
     ...
 }}
 ```
 
+- [FLAGS](#flags) are optional pattern flags, which apply to every rule of the lexer.
 - `NAME` is the name of the function that is generated by the macro. This function can be called to produce a lexer.
 - [ARGS](#args) is an optional list of arguments that are passed to the lexer.
 - `TOKEN` is the type of the tokens that the lexer produces. This can be any type, including void.
+- [PREV](#previous-token) is an optional summary of the previously emitted token, available to the rules.
 - [PATTERN](#patterns) is a pattern that the lexer matches against the input. If the pattern matches, the action is executed.
 - [ACTION](#actions) is an expression that is executed if the pattern matches. The expression must produce a token or `continue` or `break`.
 
 The rules consist of a pattern and an action resulting in a token.\
 The order of the patterns is important, as the first that matches is chosen.
+Rules shared between lexers can be defined once in a [rule set](#rule-sets).
 
 ## Patterns
 
-Patterns are matched to the beginning of the input in the order they are defined.
+Patterns are matched to the beginning of the input in the order they are defined, unless [priorities](#priorities) are given.
 
 Patterns can be the following:
 - One ore more string slice literals or constants. These strings are concatenated together, and used for regex matching.
 - A wildcard `_` that matches any single character. This does not match eof.
 - `eof`, which matches the end of the input. This is optional, and if not provided, end of file is just ignored.
-- `ws`, which matches any whitespace character.
+- `ws`, which matches any Unicode whitespace character.
+- `newline`, which matches a single line break, where `\r\n` counts as one.
+- `xid_ident` and `ascii_ident`, which match a Unicode identifier as defined by UAX #31, or an ASCII identifier.
+- `int`, `float` and `string`, which match a decimal integer, a decimal number with a fraction or exponent, and a double quoted string with `\` escapes.
+
+The regexes of the builtin patterns are also available in [`patterns`](crate::patterns), so they can be concatenated with other patterns.
+Note that `int` also matches the beginning of a `float`, so the `float` rule should come first.
+
+Patterns other than `eof` must not be able to match the empty string, like `"[0-9]*"`, as they would never consume any input.
+Such patterns make the lexer panic when it is created.
 
 Here is an example showing the different legal patterns
 ```
@@ -81,6 +93,196 @@ let tokens = lex("a ba ca S 42").into_token_vec();
 assert_eq!(tokens, vec![A, B, C, D, Num, Eof])
 ```
 
+### Beginning of line
+
+Each pattern is matched against the remaining input, so a `^` inside a pattern means the current position, and not the start of a line.
+To only match a rule at the beginning of a line, prefix it with `^` outside of the strings.
+This is useful for things like preprocessor directives and markdown headings.
+
+Actions can check the same thing with [`LexBuf::at_line_start`](crate::LexBuf::at_line_start).
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Directive, Hash, Word
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    ^ "#[a-z]+" => |_| Directive,
+    "#" => |_| Hash,
+    "[a-z]+" => |_| Word,
+}}
+
+let tokens = lex("#define x #y\n#include").into_token_vec();
+assert_eq!(tokens, vec![Directive, Word, Hash, Word, Directive]);
+```
+
+### Trailing context
+
+A pattern can be followed by `/` and a trailing context, which is a pattern that must match after it.
+The trailing context is not consumed, and is not part of the matched string or its location.
+
+This can for instance be used to tell the integer in a range `1..2` apart from a float `1.5`:
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Int, Float, Range
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    "[0-9]+" / r"\.\." => |_| Int,
+    r"[0-9]+\.[0-9]+" => |_| Float,
+    "[0-9]+" => |_| Int,
+    r"\.\." => |_| Range,
+}}
+
+let tokens = lex("1..2.5").into_token_vec();
+assert_eq!(tokens, vec![Int, Range, Float]);
+```
+
+### Delimited patterns
+
+Some literals end with a delimiter that depends on how they were opened, like raw strings `r#"..."#` or heredocs `<<EOF ... EOF`.
+These can be matched by following the pattern with `until` and a closure that computes the terminator from the opening match.
+The input is then consumed up to and including the first occurrence of the terminator.
+
+The action is provided with the text between the opening and the terminator, while the location spans the entire literal.
+If the terminator is never found, the lexer panics with a [`LexError::Unterminated`](crate::LexError::Unterminated).
+
+The same scanning is available from within actions through [`LexBuf::scan_until`](crate::LexBuf::scan_until).
+
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Raw(String), Heredoc(String)
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r##"r#*""## until |open| format!("\"{}", &open[1..open.len() - 1]) => |body| Raw(body.to_string()),
+    r"<<[A-Z]+\n" until |open| format!("\n{}", open[2..].trim_end()) => |body| Heredoc(body.to_string()),
+}}
+
+let tokens = lex("r#\"say \"hi\"\"# <<EOF\nline\nEOF").into_token_vec();
+assert_eq!(tokens, vec![Raw("say \"hi\"".to_string()), Heredoc("line".to_string())]);
+```
+
+### Guards
+
+A rule can be made conditional with a guard, written as `PATTERN if COND => ACTION`.
+The guard is evaluated before the pattern is tried, and if it does not hold, lexing falls through to the next rule,
+exactly as if the pattern had not matched.
+
+Guards can use the [args](#args) of the lexer, which can also be used to share mutable state.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Keyword, Id
+}
+use Token::*;
+
+lex_rule!{lex(strict: bool) -> Token {
+    ws => |_| continue,
+    "let" if strict => |_| Keyword,
+    "[a-z]+" => |_| Id,
+}}
+
+assert_eq!(lex("let x", true).into_token_vec(), vec![Keyword, Id]);
+assert_eq!(lex("let x", false).into_token_vec(), vec![Id, Id]);
+```
+
+### Priorities
+
+By default, the rules are tried in the order they are declared, and the first one that matches is used.
+This can be overridden with `#[priority(N)]` before a rule, where rules with a higher priority are tried first.
+Rules without the attribute have priority 0, and rules with the same priority are tried in the order they are declared.
+
+This is useful when a longer rule should win over a rule matching the start of it, which is declared before it,
+like a rule for floats over a rule for integers included from a [rule set](#rule-sets).
+Otherwise `1.5` would be lexed as the integer `1` followed by whatever `.5` is.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Int(String), Float(String)
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    "[0-9]+" => |s| Int(s.to_string()),
+    #[priority(1)] r"[0-9]+\.[0-9]+" => |s| Float(s.to_string()),
+}}
+
+assert_eq!(lex("1.5 2").into_token_vec(), vec![Float("1.5".to_string()), Int("2".to_string())]);
+```
+
+Keywords should not be given a priority over identifiers, as `"if"` would then also match the start of `iff`.
+Give the identifier rule a table of [keywords](#keywords) instead.
+
+### Flags
+
+The patterns can be modified with flags, which apply to the entire concatenated pattern of a rule, including the trailing context.
+They can be given before a single rule, or before the name of the lexer to apply to all of its rules.
+- `#[case_insensitive]` - Letters match both upper and lower case.
+- `#[ascii_classes]` - `\d`, `\w`, `\s` and `\b` only consider ASCII characters, instead of all of Unicode.
+- `#[dot_all]` - `.` also matches newlines.
+
+The builtin patterns are not affected by the flags.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Select, Id
+}
+use Token::*;
+
+const SEL: &str = "sel";
+
+lex_rule!{#[case_insensitive] lex -> Token {
+    ws => |_| continue,
+    SEL "ect" => |_| Select,
+    #[ascii_classes] r"\w+" => |_| Id,
+}}
+
+assert_eq!(lex("SeLeCt abc").into_token_vec(), vec![Select, Id]);
+```
+
+### Keywords
+
+Keywords are usually a subset of the identifiers, and writing a rule for each of them is both tedious and error prone,
+as a rule for `"in"` would also match the beginning of `input`.
+
+Instead, a rule can be given a table of keywords with `PATTERN keywords { "KEYWORD" => TOKEN, ... } => ACTION`.
+When the pattern matches, the entire lexeme is looked up in the table, and if it is a keyword, the corresponding token is produced.
+Otherwise the action is run as usual.
+The lookup uses a perfect hash table, and is case insensitive if the rule is.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    If, In, Id(String)
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    "[a-z]+" keywords {
+        "if" => If,
+        "in" => In,
+    } => |s| Id(s.to_string()),
+}}
+
+assert_eq!(lex("if input in").into_token_vec(), vec![If, Id("input".to_string()), In]);
+```
+
 ## Actions
 
 An action is a closure returning the token type provided in the macro definition.\
@@ -88,15 +290,35 @@ It will run when the pattern matches, and can be used to produce a token, skip o
 
 ### Signature
 
-There are 3 different signatures for the closure, which can be used to provide different parameters to the action:
+There are 4 different signatures for the closure, which can be used to provide different parameters to the action:
 - `|s|` - The action is provided with the matched string
 - `|s, buf|` - The action is provided with the matched string and a buffer. The buffer can be used to lex a subrule.
 - `|s, buf, loc|` - The action is provided with the matched string, a buffer, and a location. The location is the location of the matched string in the input.
+- `|s, buf, loc, caps|` - The action is additionally provided with the [`Captures`](crate::Captures) of the pattern.
+  Capture groups can be accessed by index or name, along with their locations.
 
 Only the first argument is required, the rest are optional. They can all be ignored with an underscore `_`.\
 This means that if no arguments are needed, the signature can be written as `|_|`.\
 For instance if only the location is of interest, the other arguments can be ignored with an underscore: `|_, _, loc|`.
 
+Here is an example using capture groups to parse hexadecimal numbers:
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Hex(u32)
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r"0x(?<digits>[0-9a-fA-F]+)" => |_, _, _, caps| Hex(u32::from_str_radix(&caps["digits"], 16).unwrap()),
+}}
+
+let tokens = lex("0xff 0x10").into_token_vec();
+assert_eq!(tokens, vec![Hex(255), Hex(16)]);
+```
+
 ### Action
 
 The actions themselves can be any expression that returns a token or `continues` or `breaks`.
@@ -168,6 +390,40 @@ let tokens = lex("ab", 12).into_token_vec();
 assert_eq!(tokens, vec![A, B(12), Eof]);
 ```
 
+# Previous token
+
+Some tokens can only be told apart by looking at the token before them,
+like a regex literal `/ab+/` and a division `a / b` in JavaScript.
+
+The lexer can remember a summary of the previously emitted token, by adding `, NAME: TYPE = FUNCTION` after the token type.
+The function is called with a reference to each emitted token, and the result is available to guards and actions
+as an `Option<TYPE>` with the given name. It is `None` until the first token has been emitted.
+If the token type implements `Clone`, the token itself can be remembered with `Clone::clone`.
+
+Sub rules have their own previous token.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id, Div, Regex
+}
+use Token::*;
+
+fn is_operand(token: &Token) -> bool {
+    matches!(token, Id | Regex)
+}
+
+lex_rule!{lex -> Token, operand: bool = is_operand {
+    ws => |_| continue,
+    "[a-z]+" => |_| Id,
+    "/" if operand == Some(true) => |_| Div,
+    "/[^/]+/" => |_| Regex,
+}}
+
+let tokens = lex("a / b / /ab+/").into_token_vec();
+assert_eq!(tokens, vec![Id, Div, Id, Div, Regex]);
+```
+
 # Sub Rules
 
 Sub rules are lex rules that are called from the action of another lex rule.\
@@ -212,14 +468,469 @@ lex_rule!{comment(depth: u16) -> () {
 let tokens = lex("a (* comment (* inner *) comment *) aa").into_token_vec();
 assert_eq!(tokens, vec![A, A, A, Eof]);
 ```
+
+# Rule Sets
+
+Lexers for related languages often share most of their rules.
+These can be defined once with `lex_rule_set!`, and included in any number of lexers with `include NAME`.
+The included rules behave exactly as if they were written in place of the `include`, and a set can include other sets.
+
+Rules can be named with `#[name(NAME)]`, which allows a lexer to change the included rules:
+- A later rule with the same name replaces the earlier one, at its position.
+- `#[before(NAME)]` and `#[after(NAME)]` place a rule immediately before or after the named rule, wherever it is declared.
+
+A rule set is a macro, so it can be used after its definition in the same module and its children,
+or anywhere in the crate if it is declared as `pub(crate)`.
+Paths in the rules of a set are resolved where it is included, and its actions can not use the args of the lexer.
+```
+use lexr::{lex_rule, lex_rule_set};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id, Num, Cast
+}
+use Token::*;
+
+lex_rule_set!{sql {
+    ws => |_| continue,
+    #[name(id)] "[a-z]+" => |_| Id,
+    "[0-9]+" => |_| Num,
+}}
+
+lex_rule!{ansi -> Token {
+    include sql,
+}}
+
+lex_rule!{postgres -> Token {
+    include sql,
+    #[name(id)] "[a-z_]+" => |_| Id,
+    #[before(id)] "::" => |_| Cast,
+}}
+
+assert_eq!(ansi("a 1").into_token_vec(), vec![Id, Num]);
+assert_eq!(postgres("a_b::c").into_token_vec(), vec![Id, Cast, Id]);
+```
+
+# Unreachable rules
+
+As the first matching rule is chosen, a rule placed after a broader one can never be selected.
+For instance `_` makes every rule after it unreachable, and `"[a-z]+"` makes a later `"if"` unreachable.
+
+These can be found with [`Lexer::unreachable_rules`](crate::Lexer::unreachable_rules),
+which reports each unreachable rule with the shortest input it matches, and the rule selected for that input instead.
+The rules of a lexer, in the order they are tried, are available with [`Lexer::rules`](crate::Lexer::rules).
+
+The analysis is exact for most patterns, but rules with guards are assumed to not hold,
+and rules with Unicode word boundaries or very large automata are assumed to be reachable.
+Calling it from a test is a cheap way to catch mistakes in the order of the rules.
+```
+use lexr::lex_rule;
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id, If
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    "[a-z]+" => |_| Id,
+    "if" => |_| If,
+}}
+
+let unreachable = lex("").unreachable_rules();
+assert_eq!(unreachable.len(), 1);
+assert_eq!(unreachable[0].rule.source, r#""if""#);
+assert_eq!(unreachable[0].witness.as_deref(), Some("if"));
+assert_eq!(unreachable[0].shadowed_by.as_ref().unwrap().source, r#""[a-z]+""#);
+```
+
+# Tracing
+
+When the `log` feature is enabled, the lexers trace every step they take with the [`log`](https://docs.rs/log) crate,
+at the trace level and under the target `lexr`. For each token this includes:
+- Every rule that is tried at the current position, and whether it matched or why it was skipped.
+- The rule that is selected, and the lexeme it matched.
+- What the action did: produced a token, was replaced by a keyword, continued or stopped lexing.
+
+As this goes through the `log` crate, the tracing can be enabled selectively by any logger, like `RUST_LOG=lexr=trace` with `env_logger`.
+Without the feature, the tracing is compiled away entirely.
+```text
+1:1: tried ws, it did not match
+1:1: selected "[a-z]+", it matched "if"
+1:1-2: the action of "[a-z]+" was replaced by a keyword
+```
+
+# Statistics
+
+When the `stats` feature is enabled, the lexers count how each rule performs,
+and [`Lexer::stats`](crate::Lexer::stats) reports the counts so far. For each rule this includes:
+- How many times its pattern was tried. Rules skipped by their guard or by `^` are not tried.
+- How many times it was selected, and how many bytes it consumed, including the bodies of delimited patterns.
+- The total time spent matching its pattern.
+
+The report lists the rules in the order they are tried, and is printed as a table.
+The lexers of a [`LexerBuilder`](crate::LexerBuilder) report the rules of each mode in turn, in the order the modes were added.
+Rules that were never selected are found with `Stats::unused`, which can point out dead rules, while the attempts and times show which rules are worth moving forward.
+Without the feature, nothing is counted.
+```text
+rule            attempts     matches       bytes          time
+ws                     8           3           3        4.2µs
+"[a-z]+"               5           2           4        3.1µs
+```
+
+# Serialization
+
+When the `serde` feature is enabled, [`SrcLoc`](crate::SrcLoc) and [`LexError`](crate::LexError) implement `Serialize` and `Deserialize`,
+while the reports of [`Lexer::rules`](crate::Lexer::rules), [`Lexer::unreachable_rules`](crate::Lexer::unreachable_rules) and `Lexer::stats` implement `Serialize`.
+
+If the token type is serializable, the output of a lexer can be stored or sent between processes:
+- `Lexer::write_json_lines` writes each token and its location as a line of JSON, like `[token, loc]`. They are read back with `serial::read_json_lines`.
+- `Lexer::into_binary` serializes the tokens and their locations in the compact binary format of [`postcard`](https://docs.rs/postcard). They are read back with `serial::from_binary`.
+```text
+[{"Id":"a"},{"abs_range":[0,1],"start":[1,1],"end":[1,1]}]
+["Plus",{"abs_range":[2,3],"start":[1,3],"end":[1,3]}]
+```
+
+# Async streams
+
+When the `async` feature is enabled, a lexer can lex source as it arrives, like from a network socket.
+`Lexer::stream` turns a lexer into a `Stream` of tokens and locations read from an `AsyncRead` of the [`futures`](https://docs.rs/futures) crate,
+so the same rules are used for whole strings and for streams. As only its rules are used, the lexer is created for the empty string, and any input it was created with is discarded.
+```ignore
+use futures::StreamExt;
+
+let mut tokens = lex("").stream(socket);
+while let Some(token) = tokens.next().await {
+    let (token, loc) = token?;
+    // ...
+}
+```
+
+A token is produced once the source read so far decides it, so tokens split between reads are produced after the read completing them.
+The lexer waits for more source when a rule matches all of the source read so far, or when a rule tried first could still match with more of it.
+A step waiting for more source is lexed again once it arrives, so its actions can run more than once,
+and [`LexBuf::scan_until`](crate::LexBuf::scan_until) returns an error while the terminator has not arrived.
+Reading errors and invalid UTF-8 end the stream with an error.
+
+# Parallel lexing
+
+Large sources can be lexed on several threads with [`Parallel`](crate::Parallel), which splits the source into chunks at resynchronization points,
+lexes the chunks on a pool of threads, and stitches the tokens together with their locations in the whole source.
+The points are decided by a predicate on the source, like [`Parallel::lines`](crate::Parallel::lines) for the start of each line.
+Each chunk is lexed by a new lexer, created for the empty string as only its rules are used:
+```
+# #[cfg(feature = "std")] {
+use lexr::{lex_rule, Parallel};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Field(String), Comma, Newline, Eof,
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    "," => |_| Comma,
+    "\n" => |_| Newline,
+    "\"" until |_| "\"" => |s| Field(s.to_string()),
+    "[^,\n\"]+" => |s| Field(s.to_string()),
+    eof => |_| Eof,
+}}
+
+let src = "a,\"b\nc\"\nd,e";
+let tokens = Parallel::lines().chunk_size(4).threads(2).lex(src, || lex(""));
+assert_eq!(tokens, lex(src).into_vec());
+# }
+```
+
+A predicate only needs to find likely points, as a lexeme crossing into the next chunk, like the string above, is detected.
+The chunk is then lexed again from where the lexeme ended, so a poor predicate only costs time,
+and errors from lexing a chunk that started at the wrong point are discarded without panicking.
+The tokens are the same as when lexing the whole source, as long as lexing starts over the same way where each lexeme ends.
+Lexers that remember the previous token can not start over, so they are lexed in one go on the calling thread.
+Runtime lexers can not be shared between threads, so only lexers defined with `lex_rule!` are lexed in parallel.
+
+# Layout
+
+Indentation sensitive languages can be lexed with [`Lexer::layout`](crate::Lexer::layout),
+which applies the off-side rule to the tokens of a lexer.
+The leading whitespace of each line is compared to the enclosing block, and INDENT, DEDENT and NEWLINE tokens are synthesized
+from the provided constructors. Lines without tokens, like blank lines and skipped comments, are ignored.
+
+How tabs are handled is decided by a [`TabPolicy`](crate::TabPolicy). Inconsistent indentation is reported as a [`LexError`](crate::LexError).
+
+Here is an example of a lexer for an indentation sensitive language:
+```
+use lexr::{lex_rule, Layout};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id, Colon, Indent, Dedent, Newline, Eof
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    r"[ \t\n]" => |_| continue,
+    r"#[^\n]*" => |_| continue,
+    r"[a-z]+" => |_| Id,
+    ":" => |_| Colon,
+    eof => |_| Eof,
+}}
+
+let src = "if:\n    a\n    # comment\n\n    b\nc";
+let tokens = lex(src)
+    .layout(Layout::new(|| Indent, || Dedent, || Newline))
+    .map(|res| res.unwrap().0)
+    .collect::<Vec<_>>();
+assert_eq!(tokens, vec![Id, Colon, Newline, Indent, Id, Newline, Id, Newline, Dedent, Id, Newline, Eof]);
+```
+
+# Runtime lexers
+
+When the rules are not known at compile time, like rules read from a configuration file,
+a lexer can be defined at runtime with a [`LexerBuilder`](crate::LexerBuilder).
+Each rule is a regex or a builtin pattern along with an [`Action`](crate::Action),
+which produces a token with a closure, skips the lexeme, stops lexing or switches mode.
+The rules are compiled once by [`build`](crate::LexerBuilder::build), and are then tried in order like the rules of a `lex_rule!`.
+
+Rules can be grouped in modes, where only the rules of the current mode are tried.
+A rule can push a mode, and a rule of that mode can pop it again to return to the previous mode, or switch to another mode.
+Like in a `lex_rule!`, the last added rule can be limited to the beginning of a line with [`bol`](crate::LexerBuilder::bol),
+or be given a trailing context with [`trail`](crate::LexerBuilder::trail).
+```
+use lexr::{Action, LexerBuilder};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id(String), Str(String)
+}
+use Token::*;
+
+let lexer = LexerBuilder::new()
+    .builtin("ws", Action::Skip)
+    .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+    .rule("\"", Action::push("string"))
+    .mode("string")
+    .rule("[^\"]+", Action::emit(|s, _| Str(s.to_string())))
+    .rule("\"", Action::Pop)
+    .build()
+    .unwrap();
+
+let tokens = lexer.lex("say \"hi there\"").into_token_vec();
+assert_eq!(tokens, vec![Id("say".to_string()), Str("hi there".to_string())]);
+```
+
+Like the lexers of `lex_rule!`, the lexer panics if no rule matches.
+[`RuntimeLexer::try_lex`](crate::RuntimeLexer::try_lex) returns the [`LexError`](crate::LexError) instead.
+
+# Command line tool
+
+The `lexr` binary tries out a lexer without writing any Rust. It reads a specification holding the same rules as a `lex_rule!`,
+and tokenizes the given files, or standard input:
+```text
+lexr [--format table|json|color] SPEC [INPUT]...
+```
+
+Each line of the specification is a rule, `PATTERN => ACTION`, or a comment starting with `//`.
+Patterns are string literals with the escapes of Rust, raw string literals or builtin patterns.
+They can start with `^`, and be followed by `/` and a string literal with the trailing context.
+The action is `skip`, `break`, `push MODE`, `pop`, `switch MODE` or the name of the token to produce.
+A `flags` line applies flags to every rule, and a `mode` line adds the rules after it to a mode.
+```text
+flags case_insensitive
+ws => skip
+"//[^\n]*" => skip
+r"[a-z]+" => Id
+"[0-9]+" / r"\.\." => Int
+"\"" => push string
+eof => Eof
+
+mode string
+r#"[^"]+"# => Str
+"\"" => pop
+```
+
+The tokens are printed as a table with their locations, as a line of JSON each, or by coloring the source.
+The exit code is 0 when every input was tokenized, 1 when an input contained text that no rule matched,
+2 when the arguments or the specification were invalid, and 3 when an input could not be read.
+
+`lexr import FILE` prints a flex or ocamllex specification as a specification for the tool, as described [below](#importing-flex-and-ocamllex).
+
+# Importing flex and ocamllex
+
+Lexers written for flex (`.l`) and ocamllex (`.mll`) can be brought over with [`import_flex`](crate::import_flex) and [`import_ocamllex`](crate::import_ocamllex).
+The named definitions are expanded, the patterns are translated to the syntax of the `regex` crate,
+and the start conditions of flex and the entry points of ocamllex become modes.
+The actions are only read as far as to tell which token they return, and how they change the mode.
+
+The [`ImportedLexer`](crate::ImportedLexer) is emitted as `lex_rule!` source, where every mode but the first becomes a sub rule,
+or as a specification for the [command line tool](#command-line-tool), which supports every mode change:
+```text
+lexr import [--from flex|ocamllex] [--to spec|rust] [--name NAME] FILE
+```
+
+Constructs that can not be translated, like `REJECT` or actions returning character literals, are reported with their line.
+Both tools select the longest match, while lexr selects the first rule that matches,
+so a rule that matches a prefix of what a later rule matches, like a keyword before identifiers, is reported in [`warnings`](crate::ImportedLexer::warnings) with the line of the later rule.
+The command line tool prints these warnings, and the rules should be reviewed after importing.
+```
+# #[cfg(feature = "std")] {
+let imported = lexr::import_flex(r#"
+%%
+"if"       return IF;
+[a-z]+     return ID;
+[ \t\n]+   ;
+"#).unwrap();
+
+assert_eq!(imported.to_spec(), r#"r"if" => IF
+r"[a-z]+" => ID
+r"[ \t\n]+" => skip
+"#);
+assert_eq!(imported.warnings[0].to_string(), r#"line 3: The rule matches "if", a prefix of "ifa", which the longest match selects the rule on line 4 for, while lexr selects this rule as it is tried first"#);
+# }
+```
+
+# TextMate grammars
+
+The rules of a lexer can also drive syntax highlighting in editors like VS Code.
+[`Lexer::textmate`](crate::Lexer::textmate) and [`RuntimeLexer::textmate`](crate::RuntimeLexer::textmate) export a lexer as a `.tmLanguage.json` grammar,
+given a [`TextMate`](crate::TextMate) with the name of the language and the scope highlighting each rule.
+Rules are picked by their name, their pattern, or a keyword of their keyword table.
+
+The rules are exported in order, as TextMate selects the first of the patterns that match at the same position.
+A mode pushed by a rule of a `LexerBuilder` becomes a region, which begins with the pushing rule and ends with the rules popping the mode.
+Guards, delimited patterns, switching modes and regex constructs that TextMate lacks can not be represented.
+They are listed in the [`warnings`](crate::TextMateGrammar::warnings) of the grammar, which is exported as closely as possible anyway.
+```
+# #[cfg(feature = "std")] {
+use lexr::{lex_rule, TextMate};
+
+lex_rule!{lex -> &'static str {
+    ws => |_| continue,
+    #[name(int)]
+    "[0-9]+" => |_| "int",
+    "[a-z]+" keywords {
+        "let" => "let",
+    } => |_| "id",
+}}
+
+let grammar = lex("").textmate(&TextMate::new("Calc", "source.calc")
+    .file_type("calc")
+    .scope("int", "constant.numeric")
+    .scope("let", "keyword.other"));
+
+assert!(grammar.json.contains(r#""match": "\\b(?:let)\\b",
+          "name": "keyword.other""#));
+assert!(grammar.warnings.is_empty());
+# }
+```
+
+# Highlighting
+
+The tokens of a lexer can highlight the source they were lexed from, for documentation or terminal tools.
+[`Lexer::highlight`](crate::Lexer::highlight) classifies each token with a closure, where `None` leaves it unhighlighted.
+The text between the tokens, like skipped whitespace and comments, is found through the locations of the tokens and kept as it is.
+
+The [`Highlighted`](crate::Highlighted) source is rendered as HTML, with a `<span>` of its class around each classified lexeme,
+or colored for a terminal with the styles of an [`AnsiTheme`](crate::AnsiTheme).
+Tokens collected beforehand, like the tokens of [`RuntimeLexer::try_lex`](crate::RuntimeLexer::try_lex), can be classified with [`Highlighted::new`](crate::Highlighted::new).
+```
+# #[cfg(feature = "std")] {
+use lexr::{lex_rule, AnsiTheme};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Let, Id, Int, Eq
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r"#[^\n]*" => |_| continue,
+    "[0-9]+" => |_| Int,
+    "=" => |_| Eq,
+    "[a-z]+" keywords {
+        "let" => Let,
+    } => |_| Id,
+}}
+
+let src = "let x = 1 # one";
+let highlighted = lex(src).highlight(src, |token| match token {
+    Let => Some("keyword"),
+    Int => Some("number"),
+    Id | Eq => None,
+});
+
+assert_eq!(highlighted.html(), r#"<span class="keyword">let</span> x = <span class="number">1</span> # one"#);
+assert_eq!(highlighted.ansi(&AnsiTheme::new().style("keyword", "1;34")), "\x1b[1;34mlet\x1b[0m x = 1 # one");
+# }
+```
+
+# Without the standard library
+
+The `std` feature is enabled by default. Without it, lexr is `no_std` and only needs `alloc`, for lexing on embedded targets like the command shell of a firmware.
+Lexers defined with `lex_rule!` and [`LexerBuilder`](crate::LexerBuilder) work the same, along with [`Lexer`](crate::Lexer), [`SrcLoc`](crate::SrcLoc),
+[`LexError`](crate::LexError), [`Layout`](crate::Layout) and the analysis of the rules.
+Importing, TextMate grammars, highlighting, parallel lexing, the `lexr::regex` and `lexr::lazy_static` re-exports, and the `stats`, `serde` and `async` features require the standard library, as does the command line tool.
+```toml
+[dependencies]
+lexr = { version = "0.1", default-features = false }
+```
+
+The patterns are compiled with [`regex-automata`](https://docs.rs/regex-automata), which is what the `regex` crate is built on, so they have the same syntax.
+The rules of a lexer are still compiled the first time it is used. Without the standard library no lock is taken for it, so threads using a lexer for the first time at once may each compile the rules, and the first to finish is kept.
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
 pub mod lexer;
 pub mod lex_rule;
 pub mod lex_buf;
 pub mod src_loc;
+pub mod lex_error;
+pub mod layout;
+pub mod captures;
+pub mod patterns;
+pub mod analysis;
+pub mod builder;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod import;
+#[cfg(feature = "std")]
+pub mod textmate;
+#[cfg(feature = "std")]
+pub mod highlight;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "serde")]
+pub mod serial;
+#[cfg(feature = "async")]
+pub mod stream;
+mod trace;
+mod sync;
+#[doc(hidden)]
+pub mod rule_table;
+#[doc(hidden)]
+pub mod keywords;
 
 pub use lexer::Lexer;
 pub use lex_rule::*;
 pub use lex_buf::LexBuf;
-pub use src_loc::SrcLoc;
\ No newline at end of file
+pub use src_loc::SrcLoc;
+pub use lex_error::LexError;
+pub use layout::{Layout, LayoutLexer, TabPolicy};
+pub use captures::Captures;
+pub use analysis::{RuleInfo, UnreachableRule};
+pub use builder::{Action, BuildError, LexerBuilder, RuntimeLexer};
+#[cfg(feature = "std")]
+pub use import::{import_flex, import_ocamllex, ImportError, ImportedLexer};
+#[cfg(feature = "std")]
+pub use textmate::{ExportWarning, TextMate, TextMateGrammar};
+#[cfg(feature = "std")]
+pub use highlight::{AnsiTheme, Highlighted};
+#[cfg(feature = "std")]
+pub use parallel::Parallel;
+#[cfg(feature = "async")]
+pub use stream::LexStream;
+#[cfg(feature = "stats")]
+pub use stats::{Counters, RuleStats, Stats};
+#[doc(hidden)]
+pub use stats::StatsRecorder;
+#[doc(hidden)]
+pub use keywords::KeywordTable;
+#[doc(hidden)]
+pub use rule_table::{Buffered, Outcome, Pattern, Placement, Resume, Resumed, RuleMatch, RuleSpec, RuleTable, Rules};
\ No newline at end of file
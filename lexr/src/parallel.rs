@@ -0,0 +1,212 @@
+//! Lexing of large sources in parallel.
+//!
+//! The source is split into chunks at resynchronization points, and the chunks are lexed on a pool of threads,
+//! each by a new lexer. The tokens are then stitched together in order, with their locations in the whole source.
+
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::{LexBuf, LexError, Lexer, Resume, SrcLoc};
+
+/// Decides whether a chunk can start at a byte index of the source
+type BoundaryFn = Box<dyn Fn(&str, usize) -> bool + Send + Sync>;
+
+/// Settings for lexing a source in parallel, with [`Parallel::lex`].
+///
+/// The source is split into chunks of about the chunk size, each starting at the first resynchronization point after it,
+/// as decided by the boundary predicate. A resynchronization point is where lexing can start over,
+/// like a newline that is not inside a string. Each chunk is lexed by a new lexer, and the `eof` rule only matches at the end of the source.
+///
+/// Where a lexeme crosses into the next chunk, like a string containing a newline, the point was not a resynchronization point after all.
+/// The tokens lexed from it are discarded, along with any error from lexing them, which stops the chunk rather than panicking,
+/// and the chunk is lexed again from where the lexeme ended, on the calling thread.
+/// The result is therefore the same as for lexing the source in one go, as long as lexing starts over the same way at the points where lexemes end,
+/// while the predicate only decides how well the work is split.
+///
+/// Only the source decides the points, so a new lexer must be able to take over at any of them.
+/// Lexers that carry state from one token to the next, like the previous token, are instead lexed in one go on the calling thread.
+/// Panics in actions are not caught either, so actions should not panic on input that only a chunk starting at the wrong point would see.
+pub struct Parallel {
+    boundary: BoundaryFn,
+    chunk_size: usize,
+    threads: usize,
+}
+
+/// The result of lexing a chunk, or the source from a point up to a limit
+struct Lexed<T> {
+    tokens: Vec<(T, SrcLoc)>,
+    /// Where lexing stopped
+    end: SrcLoc,
+    /// Whether lexing stopped at the limit, rather than being done
+    starved: bool,
+    /// The error that stopped lexing, which is only raised if lexing started at a resynchronization point
+    error: Option<LexError>,
+}
+
+impl Parallel {
+    /// Splits the source where `boundary` holds. It is given the whole source and the byte index of a character,
+    /// and decides whether a chunk can start there.
+    ///
+    /// The chunks are about 1 MiB, and are lexed on as many threads as are available.
+    pub fn new(boundary: impl Fn(&str, usize) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            boundary: Box::new(boundary),
+            chunk_size: 1 << 20,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    /// Splits the source at the start of lines.
+    pub fn lines() -> Self {
+        Self::new(|src, idx| src.as_bytes()[idx - 1] == b'\n')
+    }
+
+    /// Sets the size in bytes that the chunks are split at after.
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Sets the most threads lexing at a time.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Lexes `src` in parallel, with lexers created by `lexer`, and returns the tokens and locations in order.
+    ///
+    /// As only its rules are used, the lexer is created for the empty string, like `|| lex("")`.
+    /// Like the lexer, this panics if no rule matches where lexing the source in one go would reach.
+    pub fn lex<T, Ite>(&self, src: &str, lexer: impl Fn() -> Lexer<T, Ite> + Sync) -> Vec<(T, SrcLoc)>
+    where
+        T: Send,
+        Ite: Iterator<Item = (T, SrcLoc)> + Resume<Token = T>,
+    {
+        let start = SrcLoc::new((1, 1), (1, 1), (0, 0));
+        let mut first = lexer().iter;
+        if first.keeps_state() {
+            // The state at a point is only known once everything before it is lexed
+            return raise(lex_from(&mut first, src, start, usize::MAX)).tokens
+        }
+
+        let splits = self.splits(src);
+        let count = splits.len() - 1;
+        // The lines at the starts of the chunks, from the newlines in the chunks before them
+        let newlines = self.run(count, |i| src.as_bytes()[splits[i]..splits[i + 1]].iter().filter(|byte| **byte == b'\n').count());
+        let lines: Vec<usize> = newlines.iter()
+            .scan(1, |line, newlines| {
+                *line += newlines;
+                Some(*line - newlines)
+            })
+            .collect();
+        let chunks = self.run(count, |i| lex_chunk(src, &splits, lines[i], i, &lexer));
+
+        let mut tokens = Vec::new();
+        let mut end = start;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let last = i + 1 == count;
+            let lexed = if end.get_abs_loc().0 == splits[i] {
+                chunk
+            } else if end.get_abs_loc().0 < splits[i + 1] || last {
+                // A lexeme crossed into the chunk, so it is lexed again from where the lexeme ended.
+                // The last chunk is always lexed, so `eof` can match
+                let limit = if last { usize::MAX } else { splits[i + 1] };
+                lex_from(&mut lexer().iter, src, end, limit)
+            } else {
+                // A lexeme crossed the whole chunk
+                continue
+            };
+            let lexed = raise(lexed);
+            tokens.extend(lexed.tokens);
+            end = lexed.end;
+            if !lexed.starved {
+                break
+            }
+        }
+        tokens
+    }
+
+    /// Runs `task` for each of `count` chunks on the pool of threads, and returns the results in order.
+    fn run<R: Send>(&self, count: usize, task: impl Fn(usize) -> R + Sync) -> Vec<R> {
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.min(count)).map(|_| scope.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= count {
+                        break done
+                    }
+                    done.push((i, task(i)));
+                }
+            })).collect();
+            for worker in workers {
+                for (i, result) in worker.join().unwrap_or_else(|panic| panic::resume_unwind(panic)) {
+                    results[i] = Some(result);
+                }
+            }
+        });
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// The byte indices where the chunks start, followed by the length of the source.
+    fn splits(&self, src: &str) -> Vec<usize> {
+        let mut splits = vec![0];
+        let mut target = self.chunk_size;
+        while target < src.len() {
+            match (target..src.len()).find(|&idx| src.is_char_boundary(idx) && (self.boundary)(src, idx)) {
+                Some(idx) => {
+                    splits.push(idx);
+                    target = idx + self.chunk_size;
+                }
+                None => break,
+            }
+        }
+        splits.push(src.len());
+        splits
+    }
+}
+
+/// Lexes the `i`th chunk, which starts at `line`, with a new lexer.
+fn lex_chunk<T, Ite>(src: &str, splits: &[usize], line: usize, i: usize, lexer: &impl Fn() -> Lexer<T, Ite>) -> Lexed<T>
+where
+    Ite: Iterator<Item = (T, SrcLoc)> + Resume<Token = T>,
+{
+    let start = splits[i];
+    let line_start = src[..start].rfind('\n').map_or(0, |idx| idx + 1);
+    let col = src[line_start..start].chars().count() + 1;
+    let limit = if i + 2 == splits.len() { usize::MAX } else { splits[i + 1] };
+    lex_from(&mut lexer().iter, src, SrcLoc::new((line, col), (line, col), (start, start)), limit)
+}
+
+/// Lexes `src` from `start` up to the first step at or after `limit`, keeping the error that stops it, if any.
+fn lex_from<T, Ite: Resume<Token = T>>(lexer: &mut Ite, src: &str, start: SrcLoc, limit: usize) -> Lexed<T> {
+    let idx = start.get_abs_loc().0;
+    let buf = LexBuf::resumed(&src[idx..], start.start(), idx, false);
+    buf.limit.replace(limit);
+    buf.keep_errors.replace(true);
+    let shared = buf.share();
+    let mut tokens = Vec::new();
+    let step = lexer.resume(buf, &mut |lexer| {
+        for token in lexer {
+            // A step that starved is cut short by the limit, and is lexed again with the next chunk
+            if shared.is_starved() {
+                break
+            }
+            tokens.push(token);
+        }
+    });
+
+    let starved = shared.is_starved();
+    let end = if starved { step } else { shared.current_loc() };
+    Lexed { tokens, end, starved, error: shared.error.take() }
+}
+
+/// Panics with the error that stopped lexing, once it is known to be reached by lexing the source in one go.
+fn raise<T>(lexed: Lexed<T>) -> Lexed<T> {
+    if let Some(err) = &lexed.error {
+        panic!("{}", err)
+    }
+    lexed
+}
@@ -0,0 +1,46 @@
+//! The regexes of the builtin patterns.
+//!
+//! These can be concatenated with other patterns, like `"@" lexr::patterns::ASCII_IDENT`.
+
+/// Any single character, including newlines. Used by `_`.
+pub const ANY: &str = r"(?s:.)";
+
+/// The end of the input. Used by `eof`.
+pub const EOF: &str = r"\z";
+
+/// A single Unicode whitespace character, which is either `White_Space` or `Pattern_White_Space`. Used by `ws`.
+pub const WS: &str = r"[\s\p{Pattern_White_Space}]";
+
+/// A single line break, where `\r\n` counts as one. Used by `newline`.
+pub const NEWLINE: &str = r"\r\n|[\n\v\f\r\x{85}\x{2028}\x{2029}]";
+
+/// A Unicode identifier as defined by UAX #31, also allowing it to start with `_`. Used by `xid_ident`.
+pub const XID_IDENT: &str = r"[\p{XID_Start}_]\p{XID_Continue}*";
+
+/// An ASCII identifier of letters, digits and `_`, not starting with a digit. Used by `ascii_ident`.
+pub const ASCII_IDENT: &str = r"[A-Za-z_][A-Za-z0-9_]*";
+
+/// An unsigned decimal integer. Used by `int`.
+pub const INT: &str = r"[0-9]+";
+
+/// An unsigned decimal number with a fraction, an exponent or both. Used by `float`.
+pub const FLOAT: &str = r"[0-9]+(?:\.[0-9]+(?:[eE][+-]?[0-9]+)?|[eE][+-]?[0-9]+)";
+
+/// A double quoted string, where `\` escapes the following character. Used by `string`.
+pub const STRING: &str = r#""(?:[^"\\]|\\(?s:.))*""#;
+
+/// The regex of a builtin pattern, by the name it is written as in a rule, like `ws`.
+pub fn builtin(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "_" => ANY,
+        "eof" => EOF,
+        "ws" => WS,
+        "newline" => NEWLINE,
+        "xid_ident" => XID_IDENT,
+        "ascii_ident" => ASCII_IDENT,
+        "int" => INT,
+        "float" => FLOAT,
+        "string" => STRING,
+        _ => return None,
+    })
+}
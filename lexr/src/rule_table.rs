@@ -0,0 +1,439 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use regex_automata::meta::{self, Regex};
+use regex_automata::util::syntax;
+use crate::analysis::{self, Candidate, Continuation, RuleInfo, Search, UnreachableRule};
+use crate::keywords::KeywordTable;
+use crate::stats::StatsRecorder;
+use crate::sync::Lazy;
+use crate::trace::trace;
+use crate::{Captures, LexBuf, SrcLoc};
+use regex_syntax::ast::{self, Ast, ClassAscii, ClassAsciiKind, ClassPerl, ClassPerlKind, ClassSet, ClassSetItem};
+
+#[doc(hidden)]
+/// A pattern as written in a rule.
+pub enum Pattern {
+    /// One of the [builtin patterns](crate::patterns), which are not affected by flags
+    Builtin(&'static str),
+    Regex(String),
+}
+
+#[doc(hidden)]
+/// Where a rule is placed relative to the other rules.
+pub enum Placement {
+    Declared,
+    Before(&'static str),
+    After(&'static str),
+}
+
+#[doc(hidden)]
+/// The description of a rule, as generated by [`lex_rule!`](crate::lex_rule!).
+pub struct RuleSpec {
+    pub source: Cow<'static, str>,
+    pub name: Option<&'static str>,
+    pub placement: Placement,
+    pub pattern: Pattern,
+    pub trail: Option<String>,
+    pub priority: i32,
+    pub bol: bool,
+    pub case_insensitive: bool,
+    pub ascii_classes: bool,
+    pub dot_all: bool,
+    pub guarded: bool,
+    pub captures: bool,
+    pub delimited: bool,
+    pub keywords: &'static [&'static str],
+}
+
+struct Rule {
+    spec: RuleSpec,
+    regex: Regex,
+    /// The pattern that the regex is compiled from
+    source: String,
+    keywords: KeywordTable,
+    /// Built the first time the rule fails to match a partial source
+    continuation: Lazy<Option<Continuation>, Box<dyn Fn() -> Option<Continuation> + Send + Sync>>,
+}
+
+#[doc(hidden)]
+/// Implemented by the generated lexers, to give access to their rules.
+pub trait Rules {
+    fn rule_table() -> &'static RuleTable;
+    fn stats(&self) -> &StatsRecorder;
+}
+
+#[doc(hidden)]
+/// Implemented by the lexers, to give access to the buffer they lex.
+pub trait Buffered<'a> {
+    fn buf(&self) -> &LexBuf<'a>;
+}
+
+#[doc(hidden)]
+/// The lexer that [`Resume::resume`] passes on.
+pub type Resumed<'l, T> = dyn Iterator<Item = (T, SrcLoc)> + 'l;
+
+#[doc(hidden)]
+/// Implemented by the lexers, to continue lexing in another buffer with the state they have so far.
+///
+/// This lets a [`LexStream`](crate::LexStream) lex each part of its source as it arrives.
+pub trait Resume {
+    type Token;
+
+    /// Passes a lexer for `buf` to `lex`, which continues from the state of this lexer, and keeps its state afterwards.
+    ///
+    /// Returns the position where the last step began, which is where lexing continues if the buffer starved during it.
+    fn resume(&mut self, buf: LexBuf<'_>, lex: &mut dyn FnMut(&mut Resumed<'_, Self::Token>)) -> SrcLoc;
+
+    /// Whether the lexer carries state from one token to the next, like the previous token or a stack of modes,
+    /// so a new lexer can not take over in the middle of a source.
+    fn keeps_state(&self) -> bool;
+}
+
+#[doc(hidden)]
+/// What the action of a selected rule did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Token,
+    Keyword,
+    Continue,
+    Break,
+}
+
+#[doc(hidden)]
+/// A successful match of a rule.
+pub struct RuleMatch<'a> {
+    pub rule: usize,
+    pub lexeme: &'a str,
+    pub caps: Option<Captures<'a>>,
+}
+
+#[doc(hidden)]
+/// The compiled rules of a lexer, along with the order they are tried in.
+///
+/// Rules are tried by descending priority, and in the order they were declared when the priorities are equal.
+/// A rule with the name of an earlier rule replaces it, and rules can be placed before or after a named rule.
+pub struct RuleTable {
+    rules: Vec<Rule>,
+    order: Vec<usize>,
+}
+
+impl RuleTable {
+    /// Compiles the rules, panicking if any of them are invalid.
+    pub fn new(specs: Vec<RuleSpec>) -> Self {
+        Self::try_new(specs).unwrap_or_else(|(_, err)| panic!("{}", err))
+    }
+
+    /// Compiles the rules, describing the first invalid rule along with its index if there is one.
+    pub fn try_new(specs: Vec<RuleSpec>) -> Result<Self, (usize, String)> {
+        let rules = specs.into_iter().enumerate().map(|(i, spec)| {
+            let source = Self::regex_source(&spec);
+            let regex = Regex::builder()
+                .syntax(syntax::Config::new()
+                    .case_insensitive(spec.case_insensitive)
+                    .dot_matches_new_line(spec.dot_all))
+                .build(&source)
+                .map_err(|err| (i, format!("Invalid pattern {}: {}", spec.source, describe(err))))?;
+            if Self::is_nullable(&spec) {
+                return Err((i, format!("Pattern {} can match the empty string, so it would never consume any input", spec.source)));
+            }
+            let keywords = KeywordTable::new(spec.keywords, spec.case_insensitive);
+            let (regex_source, case_insensitive, dot_all) = (source.clone(), spec.case_insensitive, spec.dot_all);
+            let continuation = Lazy::new(Box::new(move || Continuation::new(&Candidate {
+                regex: &regex_source,
+                case_insensitive,
+                dot_all,
+            })) as Box<dyn Fn() -> _ + Send + Sync>);
+            Ok(Rule { spec, regex, source, keywords, continuation })
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        // Rules in declaration order first, so rules can be placed relative to rules declared after them
+        let mut order: Vec<usize> = Vec::with_capacity(rules.len());
+        let position = |order: &Vec<usize>, name: Option<&str>| {
+            name.and_then(|name| order.iter().position(|i| rules[*i].spec.name == Some(name)))
+        };
+        for (i, rule) in rules.iter().enumerate().filter(|(_, rule)| matches!(rule.spec.placement, Placement::Declared)) {
+            match position(&order, rule.spec.name) {
+                Some(pos) => order[pos] = i,
+                None => order.push(i),
+            }
+        }
+
+        for (i, rule) in rules.iter().enumerate() {
+            let (target, offset) = match rule.spec.placement {
+                Placement::Declared => continue,
+                Placement::Before(target) => (target, 0),
+                Placement::After(target) => (target, 1),
+            };
+            if let Some(pos) = position(&order, rule.spec.name) {
+                order.remove(pos);
+            }
+            let pos = position(&order, Some(target))
+                .ok_or_else(|| (i, format!("No rule named '{}' to place {} relative to", target, rule.spec.source)))?;
+            order.insert(pos + offset, i);
+        }
+
+        order.sort_by_key(|i| core::cmp::Reverse(rules[*i].spec.priority));
+
+        Ok(Self { rules, order })
+    }
+
+    /// Checks if the lexeme of a rule can be empty. Only `eof` is allowed to be.
+    fn is_nullable(spec: &RuleSpec) -> bool {
+        let pattern = match &spec.pattern {
+            Pattern::Builtin(builtin) if *builtin == crate::patterns::EOF => return false,
+            Pattern::Builtin(builtin) => builtin.to_string(),
+            Pattern::Regex(regex) if spec.ascii_classes => ascii_classes(regex),
+            Pattern::Regex(regex) => regex.clone(),
+        };
+
+        regex_syntax::Parser::new().parse(&pattern)
+            .is_ok_and(|hir| hir.properties().minimum_len() == Some(0))
+    }
+
+    fn regex_source(spec: &RuleSpec) -> String {
+        let user_pattern = |pattern: &str| if spec.ascii_classes { ascii_classes(pattern) } else { pattern.to_string() };
+
+        let mut source = match &spec.pattern {
+            Pattern::Builtin(builtin) => format!("^(?:{})", builtin),
+            Pattern::Regex(regex) => format!("^(?:{})", user_pattern(regex)),
+        };
+
+        if let Some(trail) = &spec.trail {
+            source.push_str(&format!("(?P<__lexr_trail>{})", user_pattern(trail)));
+        }
+        source
+    }
+
+    /// The rules with their indices, in the order they are tried.
+    #[cfg(feature = "std")]
+    pub(crate) fn specs(&self) -> impl Iterator<Item = (usize, &RuleSpec)> {
+        self.order.iter().map(|i| (*i, &self.rules[*i].spec))
+    }
+
+    /// Information about the rules, in the order they are tried.
+    pub fn rules(&self) -> Vec<RuleInfo> {
+        self.order.iter().map(|i| self.info(*i)).collect()
+    }
+
+    fn info(&self, i: usize) -> RuleInfo {
+        let rule = &self.rules[i];
+        RuleInfo {
+            index: i,
+            source: rule.spec.source.to_string(),
+            name: rule.spec.name,
+            priority: rule.spec.priority,
+            regex: rule.source.clone(),
+        }
+    }
+
+    /// Finds the rules that are never selected, as the rules tried before them match everything they do.
+    ///
+    /// Rules with guards are assumed to not hold, and beginning of line rules only hide other beginning of line rules.
+    /// Rules too complex to analyze are assumed to be reachable.
+    pub fn unreachable_rules(&self) -> Vec<UnreachableRule> {
+        let dfas = self.order.iter().map(|i| {
+            let rule = &self.rules[*i];
+            analysis::build_dfa(&Candidate {
+                regex: &rule.source,
+                case_insensitive: rule.spec.case_insensitive,
+                dot_all: rule.spec.dot_all,
+            })
+        }).collect::<Vec<_>>();
+
+        let mut unreachable = Vec::new();
+        for (pos, i) in self.order.iter().enumerate() {
+            let (spec, Some(dfa)) = (&self.rules[*i].spec, &dfas[pos]) else { continue };
+
+            let earlier = self.order[..pos].iter().zip(&dfas)
+                .filter(|(j, _)| !self.rules[**j].spec.guarded && (!self.rules[**j].spec.bol || spec.bol))
+                .filter_map(|(j, dfa)| dfa.as_ref().map(|dfa| (*j, dfa)))
+                .collect::<Vec<_>>();
+
+            let earlier_dfas = earlier.iter().map(|(_, dfa)| *dfa).collect::<Vec<_>>();
+            if let Search::Unreachable = analysis::search(dfa, &earlier_dfas) {
+                let witness = analysis::shortest_match(dfa);
+                let shadowed_by = witness.as_ref().and_then(|witness| {
+                    earlier.iter().find(|(j, _)| self.rules[*j].regex.is_match(witness)).map(|(j, _)| self.info(*j))
+                });
+                unreachable.push(UnreachableRule { rule: self.info(*i), witness, shadowed_by });
+            }
+        }
+
+        unreachable
+    }
+
+    /// The index of the keyword of the rule that `lexeme` is, if any.
+    pub fn keyword(&self, rule: usize, lexeme: &str) -> Option<usize> {
+        self.rules[rule].keywords.get(lexeme)
+    }
+
+    /// Finds the first rule that matches the beginning of the remaining source.
+    ///
+    /// `guard` is called with the index of each guarded rule before it is tried.
+    /// When more source may follow, the buffer is starved instead if that source could change which rule matches, or how much.
+    pub fn select<'a>(&self, buf: &LexBuf<'a>, stats: &StatsRecorder, guard: impl Fn(usize) -> bool) -> Option<RuleMatch<'a>> {
+        let src: &'a str = *buf.source.borrow();
+        let at_line_start = buf.at_line_start();
+        let partial = *buf.partial.borrow();
+
+        for &i in &self.order {
+            let rule = &self.rules[i];
+            if rule.spec.bol && !at_line_start {
+                trace!("{}: skipped {}, not at the beginning of a line", buf.current_loc(), rule.spec.source);
+                continue
+            }
+            if rule.spec.guarded && !guard(i) {
+                trace!("{}: skipped {}, the guard does not hold", buf.current_loc(), rule.spec.source);
+                continue
+            }
+
+            // The end of the whole match is kept, as the trailing context can reach the end of a partial source too
+            let found = stats.attempt(i, || if rule.spec.captures || rule.spec.trail.is_some() {
+                let mut caps = rule.regex.create_captures();
+                rule.regex.captures(src, &mut caps);
+                caps.get_match().map(|mat| {
+                    // The trailing context is not part of the lexeme
+                    let end = mat.end();
+                    let lexeme_end = caps.get_group_by_name("__lexr_trail").map_or(end, |trail| trail.start);
+                    let loc = buf.current_loc();
+                    let caps = Captures::new(caps, &src[..lexeme_end], loc.start(), loc.get_abs_loc().0);
+                    (end, RuleMatch { rule: i, lexeme: &src[..lexeme_end], caps: Some(caps) })
+                })
+            } else {
+                rule.regex.find(src).map(|mat| (mat.end(), RuleMatch { rule: i, lexeme: &src[mat.range()], caps: None }))
+            });
+
+            match found {
+                // Empty matches never make progress, so they are skipped in favor of later rules, except at eof
+                Some((_, found)) if found.lexeme.is_empty() && !src.is_empty() => {
+                    trace!("{}: skipped {}, it matched the empty string", buf.current_loc(), rule.spec.source);
+                }
+                Some((end, _)) if partial && end == src.len() => {
+                    trace!("{}: waiting for more source, as {} matched all of it", buf.current_loc(), rule.spec.source);
+                    buf.starve_if_partial();
+                    return None
+                }
+                Some((_, found)) => {
+                    trace!("{}: selected {}, it matched {:?}", buf.current_loc(), rule.spec.source, found.lexeme);
+                    return Some(found)
+                }
+                None if partial && self.could_match_later(i, src) => {
+                    trace!("{}: waiting for more source, as {} could match with it", buf.current_loc(), rule.spec.source);
+                    buf.starve_if_partial();
+                    return None
+                }
+                None => trace!("{}: tried {}, it did not match", buf.current_loc(), rule.spec.source),
+            }
+        }
+
+        trace!("{}: no rule matched", buf.current_loc());
+        None
+    }
+
+    /// Whether a rule that does not match `src` could match it once more source follows.
+    fn could_match_later(&self, rule: usize, src: &str) -> bool {
+        // Rules too complex for the automaton are assumed to possibly match
+        self.rules[rule].continuation.as_ref().is_none_or(|continuation| continuation.is_prefix(src))
+    }
+
+    /// Records what the action of a selected rule did.
+    #[allow(unused_variables)]
+    pub fn outcome(&self, rule: usize, loc: SrcLoc, outcome: Outcome) {
+        trace!("{}: the action of {} {}", loc, self.rules[rule].spec.source, match outcome {
+            Outcome::Token => "produced a token",
+            Outcome::Keyword => "was replaced by a keyword",
+            Outcome::Continue => "continued",
+            Outcome::Break => "stopped lexing",
+        });
+    }
+}
+
+/// Describes why a pattern could not be compiled.
+fn describe(err: meta::BuildError) -> String {
+    if let Some(limit) = err.size_limit() {
+        format!("Compiled regex exceeds size limit of {} bytes.", limit)
+    } else if let Some(err) = err.syntax_error() {
+        err.to_string()
+    } else {
+        err.to_string()
+    }
+}
+
+/// Rewrites the Perl classes and word boundaries of a pattern to only match ASCII.
+///
+/// Disabling Unicode for the entire pattern is not possible, as classes like `[^a]` could then match invalid UTF-8.
+pub(crate) fn ascii_classes(pattern: &str) -> String {
+    // Invalid patterns are left as they are, so the error is reported when compiling them
+    let Ok(mut ast) = ast::parse::Parser::new().parse(pattern) else {
+        return pattern.to_string()
+    };
+
+    ascii_ast(&mut ast);
+    let mut source = String::new();
+    ast::print::Printer::new().print(&ast, &mut source).unwrap();
+    source
+}
+
+fn ascii_ast(node: &mut Ast) {
+    match node {
+        Ast::ClassPerl(perl) => {
+            *node = Ast::class_bracketed(ast::ClassBracketed {
+                span: perl.span,
+                negated: false,
+                kind: ClassSet::Item(ascii_class(perl)),
+            });
+        }
+        Ast::ClassBracketed(class) => ascii_set(&mut class.kind),
+        Ast::Assertion(assertion) if !matches!(assertion.kind,
+            ast::AssertionKind::StartLine | ast::AssertionKind::EndLine | ast::AssertionKind::StartText | ast::AssertionKind::EndText) => {
+            // Word boundaries are wrapped in (?-u:...)
+            let span = assertion.span;
+            let item = |kind| ast::FlagsItem { span, kind };
+            *node = Ast::group(ast::Group {
+                span,
+                kind: ast::GroupKind::NonCapturing(ast::Flags {
+                    span,
+                    items: vec![item(ast::FlagsItemKind::Negation), item(ast::FlagsItemKind::Flag(ast::Flag::Unicode))],
+                }),
+                ast: Box::new(node.clone()),
+            });
+        }
+        Ast::Repetition(repetition) => ascii_ast(&mut repetition.ast),
+        Ast::Group(group) => ascii_ast(&mut group.ast),
+        Ast::Alternation(alternation) => alternation.asts.iter_mut().for_each(ascii_ast),
+        Ast::Concat(concat) => concat.asts.iter_mut().for_each(ascii_ast),
+        _ => {}
+    }
+}
+
+fn ascii_set(set: &mut ClassSet) {
+    match set {
+        ClassSet::Item(item) => ascii_set_item(item),
+        ClassSet::BinaryOp(op) => {
+            ascii_set(&mut op.lhs);
+            ascii_set(&mut op.rhs);
+        }
+    }
+}
+
+fn ascii_set_item(item: &mut ClassSetItem) {
+    match item {
+        ClassSetItem::Perl(perl) => *item = ascii_class(perl),
+        ClassSetItem::Bracketed(class) => ascii_set(&mut class.kind),
+        ClassSetItem::Union(union) => union.items.iter_mut().for_each(ascii_set_item),
+        _ => {}
+    }
+}
+
+fn ascii_class(perl: &ClassPerl) -> ClassSetItem {
+    let kind = match perl.kind {
+        ClassPerlKind::Digit => ClassAsciiKind::Digit,
+        ClassPerlKind::Space => ClassAsciiKind::Space,
+        ClassPerlKind::Word => ClassAsciiKind::Word,
+    };
+    ClassSetItem::Ascii(ClassAscii { span: perl.span, kind, negated: perl.negated })
+}
@@ -0,0 +1,48 @@
+//! Serialization of token streams, available with the `serde` feature.
+//!
+//! A stream is the output of [`Lexer::into_vec`](crate::Lexer::into_vec), a token and its location for each token.
+//! It can be written as JSON Lines, with each line being an array of the token and its location,
+//! or in the compact binary format of [`postcard`](https://docs.rs/postcard).
+
+use std::io::{self, BufRead, Write};
+use serde::{de::DeserializeOwned, Serialize};
+use crate::{Lexer, SrcLoc};
+
+impl<T: Serialize, Ite: Iterator<Item = (T, SrcLoc)>> Lexer<T, Ite> {
+    /// Writes each token and its location as a line of JSON, like `[token, loc]`.
+    pub fn write_json_lines(self, mut writer: impl Write) -> io::Result<()> {
+        for token in self {
+            serde_json::to_writer(&mut writer, &token)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+
+    /// Serializes the tokens and their locations in a compact binary format.
+    ///
+    /// Read them back with [`from_binary`].
+    pub fn into_binary(self) -> io::Result<Vec<u8>> {
+        postcard::to_stdvec(&self.into_vec()).map_err(invalid_data)
+    }
+}
+
+/// Reads tokens and their locations written by [`Lexer::write_json_lines`]. Blank lines are skipped.
+pub fn read_json_lines<T: DeserializeOwned>(reader: impl BufRead) -> io::Result<Vec<(T, SrcLoc)>> {
+    let mut tokens = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            tokens.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads tokens and their locations serialized by [`Lexer::into_binary`].
+pub fn from_binary<T: DeserializeOwned>(bytes: &[u8]) -> io::Result<Vec<(T, SrcLoc)>> {
+    postcard::from_bytes(bytes).map_err(invalid_data)
+}
+
+fn invalid_data(err: postcard::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
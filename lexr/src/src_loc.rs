@@ -1,6 +1,7 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A location in the source code
 pub struct SrcLoc {
     abs_range: (usize, usize),
@@ -61,7 +62,7 @@ impl SrcLoc {
 }
 
 impl Display for SrcLoc {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let (start_line, start_col, end_line, end_col) = self.get_loc();
         if start_line == end_line && start_col == end_col {
             write!(f, "{}:{}", start_line, start_col)
@@ -74,7 +75,7 @@ impl Display for SrcLoc {
 }
 
 impl PartialOrd for SrcLoc {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         if self.start.0 == other.start.0 {
             self.start.1.partial_cmp(&other.start.1)
         } else {
@@ -0,0 +1,120 @@
+//! Per rule statistics, collected when the `stats` feature is enabled.
+
+use core::time::Duration;
+#[cfg(feature = "stats")]
+use std::{cell::RefCell, fmt::Display, time::Instant};
+#[cfg(feature = "stats")]
+use crate::RuleInfo;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The counters of a single rule.
+pub struct Counters {
+    /// The number of times the pattern was tried. Rules skipped by their guard or by not being at the beginning of a line are not tried
+    pub attempts: u64,
+    /// The number of times the rule was selected
+    pub matches: u64,
+    /// The number of bytes consumed by the rule, including the bodies of delimited patterns
+    pub bytes: u64,
+    /// The total time spent matching the pattern
+    pub time: Duration,
+}
+
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The statistics of a single rule.
+pub struct RuleStats {
+    /// The rule the statistics are for
+    pub rule: RuleInfo,
+    /// The counters of the rule
+    pub counters: Counters,
+}
+
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The statistics of every rule of a lexer, in the order they are tried.
+///
+/// It is printed as a table with one row per rule.
+pub struct Stats {
+    /// The statistics of each rule
+    pub rules: Vec<RuleStats>,
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    /// The rules that were never selected
+    pub fn unused(&self) -> impl Iterator<Item = &RuleStats> {
+        self.rules.iter().filter(|rule| rule.counters.matches == 0)
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.rules.iter().map(|rule| rule.rule.source.len()).chain([4]).max().unwrap();
+        writeln!(f, "{:<width$}  {:>10}  {:>10}  {:>10}  {:>12}", "rule", "attempts", "matches", "bytes", "time")?;
+        for rule in &self.rules {
+            let counters = &rule.counters;
+            writeln!(f, "{:<width$}  {:>10}  {:>10}  {:>10}  {:>12}",
+                rule.rule.source, counters.attempts, counters.matches, counters.bytes, format!("{:?}", counters.time))?;
+        }
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+#[derive(Default)]
+/// Collects the statistics of a lexer. This does nothing without the `stats` feature.
+pub struct StatsRecorder {
+    #[cfg(feature = "stats")]
+    counters: RefCell<Vec<Counters>>,
+}
+
+impl StatsRecorder {
+    /// Runs and times an attempt to match the pattern of a rule.
+    #[allow(unused_variables)]
+    pub fn attempt<T>(&self, rule: usize, attempt: impl FnOnce() -> Option<T>) -> Option<T> {
+        #[cfg(feature = "stats")]
+        {
+            let start = Instant::now();
+            let found = attempt();
+            let elapsed = start.elapsed();
+            let mut counters = self.counters.borrow_mut();
+            let counters = Self::get(&mut counters, rule);
+            counters.attempts += 1;
+            counters.time += elapsed;
+            found
+        }
+
+        #[cfg(not(feature = "stats"))]
+        attempt()
+    }
+
+    /// Records that a rule was selected, and consumed the bytes in `range`.
+    #[allow(unused_variables)]
+    pub fn selected(&self, rule: usize, range: (usize, usize)) {
+        #[cfg(feature = "stats")]
+        {
+            let mut counters = self.counters.borrow_mut();
+            let counters = Self::get(&mut counters, rule);
+            counters.matches += 1;
+            counters.bytes += (range.1 - range.0) as u64;
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    fn get(counters: &mut Vec<Counters>, rule: usize) -> &mut Counters {
+        if counters.len() <= rule {
+            counters.resize(rule + 1, Counters::default());
+        }
+        &mut counters[rule]
+    }
+
+    #[cfg(feature = "stats")]
+    /// The counters of a rule
+    pub fn counters(&self, rule: usize) -> Counters {
+        self.counters.borrow().get(rule).cloned().unwrap_or_default()
+    }
+}
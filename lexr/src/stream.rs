@@ -0,0 +1,136 @@
+//! Lexing of async readers, available with the `async` feature.
+//!
+//! A [`LexStream`] reads its source in parts, and lexes each part as it arrives with the rules of an ordinary lexer.
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use crate::{LexBuf, Lexer, Resume, SrcLoc};
+
+/// The most bytes read from the reader at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A [`Stream`] of the tokens and locations lexed from an [`AsyncRead`], created by [`Lexer::stream`].
+///
+/// The source is lexed as it arrives. A token is produced once the following source can no longer change it,
+/// so a lexeme split between reads is produced after the read completing it, and the `eof` rule matches when the reader ends.
+///
+/// Reading errors and invalid UTF-8 are produced as errors, after which the stream ends.
+/// Like the lexer, the stream panics if no rule matches.
+pub struct LexStream<Ite: Resume, R> {
+    lexer: Ite,
+    reader: R,
+    chunk: Box<[u8]>,
+    /// The read bytes that end in an incomplete UTF-8 sequence
+    undecoded: Vec<u8>,
+    /// The decoded source that is not lexed yet
+    pending: String,
+    /// The location of the start of `pending`
+    pos: SrcLoc,
+    tokens: VecDeque<(Ite::Token, SrcLoc)>,
+    /// An error to produce after the tokens lexed before it
+    error: Option<io::Error>,
+    done: bool,
+}
+
+// The fields are never pinned, as the reader is required to be Unpin
+impl<Ite: Resume, R> Unpin for LexStream<Ite, R> {}
+
+impl<T, Ite: Iterator<Item = (T, SrcLoc)> + Resume<Token = T>> Lexer<T, Ite> {
+    /// Lexes the source read from `reader`, as a [`Stream`] of tokens and locations. Requires the `async` feature.
+    ///
+    /// Only the rules and arguments of the lexer are used, and the input it was created with is discarded,
+    /// so the lexer is usually created for the empty string. See [`LexStream`] for details.
+    pub fn stream<R: AsyncRead + Unpin>(self, reader: R) -> LexStream<Ite, R> {
+        LexStream {
+            lexer: self.iter,
+            reader,
+            chunk: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            undecoded: Vec::new(),
+            pending: String::new(),
+            pos: SrcLoc::new((1, 1), (1, 1), (0, 0)),
+            tokens: VecDeque::new(),
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<Ite: Resume, R> LexStream<Ite, R> {
+    /// Lexes the pending source, up to where it can not decide the next token when `partial`.
+    fn lex(&mut self, partial: bool) {
+        let Self { lexer, pending, pos, tokens, .. } = self;
+        let buf = LexBuf::resumed(pending, pos.start(), pos.get_abs_loc().0, partial);
+        let shared = buf.share();
+        let step = lexer.resume(buf, &mut |lexer| {
+            for token in lexer {
+                // The token of a step that starved is incomplete, and the step is lexed again with more source
+                if shared.is_starved() {
+                    break
+                }
+                tokens.push_back(token);
+            }
+        });
+
+        // The lexer only stops without starving when it is done
+        let starved = shared.is_starved();
+        let end = if starved { step } else { shared.current_loc() };
+        drop(shared);
+        self.pending.drain(..end.get_abs_loc().0 - self.pos.get_abs_loc().0);
+        self.pos = end;
+        self.done = !starved;
+    }
+
+    /// Decodes the read bytes, up to an incomplete UTF-8 sequence at the end, or up to invalid UTF-8.
+    fn decode(&mut self, read: usize) -> io::Result<()> {
+        self.undecoded.extend_from_slice(&self.chunk[..read]);
+        let (valid, result) = match std::str::from_utf8(&self.undecoded) {
+            Ok(text) => (text.len(), Ok(())),
+            Err(err) if err.error_len().is_none() => (err.valid_up_to(), Ok(())),
+            Err(err) => (err.valid_up_to(), Err(io::Error::new(io::ErrorKind::InvalidData, err))),
+        };
+        self.pending.push_str(std::str::from_utf8(&self.undecoded[..valid]).unwrap());
+        self.undecoded.drain(..valid);
+        result
+    }
+}
+
+impl<Ite: Resume, R: AsyncRead + Unpin> Stream for LexStream<Ite, R> {
+    type Item = io::Result<(Ite::Token, SrcLoc)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(token) = this.tokens.pop_front() {
+                return Poll::Ready(Some(Ok(token)))
+            }
+            if this.done {
+                return Poll::Ready(None)
+            }
+            if let Some(err) = this.error.take() {
+                this.done = true;
+                return Poll::Ready(Some(Err(err)))
+            }
+
+            let read = match Pin::new(&mut this.reader).poll_read(cx, &mut this.chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(result) => result,
+            };
+            match read {
+                // The reader has ended, so the rest is lexed as a whole, where eof can match
+                Ok(0) if this.undecoded.is_empty() => this.lex(false),
+                Ok(0) => this.error = Some(io::Error::new(io::ErrorKind::InvalidData, "The source ends in an incomplete UTF-8 sequence")),
+                Ok(read) => {
+                    // The source decoded before invalid UTF-8 is still lexed
+                    this.error = this.decode(read).err();
+                    this.lex(true);
+                }
+                Err(err) => this.error = Some(err),
+            }
+        }
+    }
+}
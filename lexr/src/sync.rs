@@ -0,0 +1,35 @@
+//! Synchronization that works with and without the standard library.
+//!
+//! Without it, the lazy values of `regex-automata` are used instead, which may initialize a value more than once
+//! when several threads race to do it, and keep the first.
+
+use core::ops::Deref;
+
+#[doc(hidden)]
+/// A value that is initialized the first time it is used, like the rules of a generated lexer.
+pub struct Lazy<T, F = fn() -> T> {
+    #[cfg(feature = "std")]
+    cell: std::sync::LazyLock<T, F>,
+    #[cfg(not(feature = "std"))]
+    cell: regex_automata::util::lazy::Lazy<T, F>,
+}
+
+impl<T, F: Fn() -> T> Lazy<T, F> {
+    pub const fn new(init: F) -> Self {
+        #[cfg(feature = "std")]
+        return Self { cell: std::sync::LazyLock::new(init) };
+        #[cfg(not(feature = "std"))]
+        return Self { cell: regex_automata::util::lazy::Lazy::new(init) };
+    }
+}
+
+impl<T, F: Fn() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        #[cfg(feature = "std")]
+        return std::sync::LazyLock::force(&self.cell);
+        #[cfg(not(feature = "std"))]
+        return regex_automata::util::lazy::Lazy::get(&self.cell);
+    }
+}
@@ -0,0 +1,443 @@
+//! Exports lexers as TextMate grammars, for syntax highlighting in editors like VS Code.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use regex_syntax::ast::{self, Ast, ClassSet, ClassSetBinaryOpKind, ClassSetItem, Flag, FlagsItemKind};
+use crate::patterns;
+use crate::rule_table::{ascii_classes, Pattern, RuleSpec, RuleTable};
+
+#[derive(Clone, Debug, Default)]
+/// The settings of an exported TextMate grammar, along with the scopes that highlight the rules.
+///
+/// Rules without a scope are still exported, so they keep later rules from matching within their lexemes.
+pub struct TextMate {
+    name: String,
+    scope_name: String,
+    file_types: Vec<String>,
+    scopes: HashMap<String, String>,
+}
+
+impl TextMate {
+    /// A grammar for the language with the display name `name`, and the root scope `scope_name`, like `source.calc`.
+    pub fn new(name: &str, scope_name: &str) -> Self {
+        Self { name: name.to_string(), scope_name: scope_name.to_string(), ..Self::default() }
+    }
+
+    /// Adds an extension, without the dot, of the files that the grammar applies to.
+    pub fn file_type(mut self, extension: &str) -> Self {
+        self.file_types.push(extension.to_string());
+        self
+    }
+
+    /// Highlights a rule with `scope`, like `keyword.control` or `constant.numeric`.
+    ///
+    /// The rule is given by its name, its pattern as written, or its regex without quotes.
+    /// A keyword of a [keyword table](crate#keywords), or the name of a mode of a [`LexerBuilder`](crate::LexerBuilder), can also be given.
+    pub fn scope(mut self, rule: &str, scope: &str) -> Self {
+        self.scopes.insert(rule.to_string(), scope.to_string());
+        self
+    }
+
+    fn scope_of(&self, spec: &RuleSpec) -> Option<&str> {
+        let regex = match &spec.pattern {
+            Pattern::Regex(regex) => Some(regex.as_str()),
+            Pattern::Builtin(_) => None,
+        };
+        [spec.name, Some(&spec.source), regex].into_iter().flatten()
+            .find_map(|key| self.scopes.get(key))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A rule or mode that could not be exported faithfully.
+pub struct ExportWarning {
+    /// The pattern of the rule as written, or the name of the mode
+    pub source: String,
+    pub message: String,
+}
+
+impl Display for ExportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An exported TextMate grammar.
+pub struct TextMateGrammar {
+    /// The grammar, as the contents of a `.tmLanguage.json` file
+    pub json: String,
+    /// The rules and modes that could not be exported faithfully
+    pub warnings: Vec<ExportWarning>,
+}
+
+/// What a rule does to the mode when it is selected
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Transition {
+    None,
+    Break,
+    Push(usize),
+    Pop,
+    Switch(usize),
+}
+
+/// The rules of a mode to export.
+pub(crate) struct ExportMode<'a> {
+    pub name: &'a str,
+    pub table: &'a RuleTable,
+    /// The transition of each rule, by its index in the table. Rules without one do not change the mode
+    pub transitions: Vec<Transition>,
+}
+
+impl ExportMode<'_> {
+    fn transition(&self, rule: usize) -> Transition {
+        self.transitions.get(rule).copied().unwrap_or(Transition::None)
+    }
+}
+
+/// How the region of a pushed mode ends
+struct End {
+    pattern: String,
+    scope: Option<String>,
+    /// Whether the other rules of the mode are tried before the end pattern
+    last: bool,
+}
+
+impl TextMate {
+    pub(crate) fn export(&self, modes: &[ExportMode]) -> TextMateGrammar {
+        let mut warnings = Vec::new();
+        let mut warn = |source: &str, message: String| warnings.push(ExportWarning { source: source.to_string(), message });
+
+        let pushed = |target: usize| modes.iter().any(|mode| mode.table.specs().any(|(index, _)| mode.transition(index) == Transition::Push(target)));
+        let ends = modes.iter().enumerate()
+            .map(|(i, mode)| if pushed(i) { Some(self.end(mode, &mut warn)) } else { None })
+            .collect::<Vec<_>>();
+
+        let mut repository = Vec::new();
+        for (i, mode) in modes.iter().enumerate() {
+            let mut rules = Vec::new();
+            for (index, spec) in mode.table.specs() {
+                let transition = mode.transition(index);
+                // The rules popping a mode end its region instead, and popping the initial mode does nothing
+                if i > 0 && transition == Transition::Pop {
+                    continue
+                }
+                let Some(regex) = pattern(spec, &mut warn) else { continue };
+                let scope = self.scope_of(spec);
+
+                if !spec.keywords.is_empty() {
+                    rules.extend(self.keywords(spec));
+                }
+
+                let mut rule = Vec::new();
+                match transition {
+                    Transition::Push(target) => {
+                        let end = ends[target].as_ref().unwrap();
+                        rule.push(("begin", Json::Str(regex)));
+                        if let Some(scope) = scope {
+                            rule.push(("beginCaptures", captures(scope)));
+                        }
+                        rule.push(("end", Json::Str(end.pattern.clone())));
+                        if let Some(scope) = &end.scope {
+                            rule.push(("endCaptures", captures(scope)));
+                        }
+                        if let Some(scope) = self.scopes.get(modes[target].name) {
+                            rule.push(("name", Json::Str(scope.clone())));
+                        }
+                        if end.last {
+                            rule.push(("applyEndPatternLast", Json::Int(1)));
+                        }
+                        rule.push(("patterns", Json::Arr(vec![include(modes[target].name)])));
+                    }
+                    transition => {
+                        match transition {
+                            Transition::Switch(target) => warn(&spec.source, format!("Switching to mode {} can not be represented, so the rule is exported without it", modes[target].name)),
+                            Transition::Break => warn(&spec.source, "Stopping can not be represented, so the rule is exported without it".to_string()),
+                            _ => {}
+                        }
+                        rule.push(("match", Json::Str(regex)));
+                        if let Some(scope) = scope {
+                            rule.push(("name", Json::Str(scope.to_string())));
+                        }
+                    }
+                }
+                rules.push(Json::obj(rule));
+            }
+            repository.push((mode.name.to_string(), Json::obj(vec![("patterns", Json::Arr(rules))])));
+        }
+
+        let grammar = Json::obj(vec![
+            ("name", Json::Str(self.name.clone())),
+            ("scopeName", Json::Str(self.scope_name.clone())),
+            ("fileTypes", Json::Arr(self.file_types.iter().cloned().map(Json::Str).collect())),
+            ("patterns", Json::Arr(vec![include(modes[0].name)])),
+            ("repository", Json::Obj(repository)),
+        ]);
+
+        let mut json = String::new();
+        grammar.write(&mut json, 0);
+        json.push('\n');
+        TextMateGrammar { json, warnings }
+    }
+
+    /// The end of the region of a mode, from the rules that pop it.
+    fn end(&self, mode: &ExportMode, warn: &mut impl FnMut(&str, String)) -> End {
+        let pops = mode.table.specs().map(|(index, _)| mode.transition(index) == Transition::Pop).collect::<Vec<_>>();
+        let patterns = mode.table.specs()
+            .filter(|(index, _)| mode.transition(*index) == Transition::Pop)
+            .filter_map(|(_, spec)| pattern(spec, warn).map(|pattern| (pattern, self.scope_of(spec))))
+            .collect::<Vec<_>>();
+
+        if patterns.is_empty() {
+            // The mode is never popped, so its region never ends
+            return End { pattern: "(?!)".to_string(), scope: None, last: false }
+        }
+
+        let scope = patterns[0].1;
+        let scope = if patterns.iter().all(|(_, other)| *other == scope) { scope.map(str::to_string) } else {
+            warn(mode.name, "The rules popping the mode have different scopes, so the end of its region is not highlighted".to_string());
+            None
+        };
+
+        // TextMate tries the end pattern either before or after all the other rules of the region
+        let before = pops.iter().take_while(|pop| **pop).count();
+        let after = pops.iter().rev().take_while(|pop| **pop).count();
+        let count = pops.iter().filter(|pop| **pop).count();
+        let last = before == 0;
+        if before != count && after != count {
+            warn(mode.name, format!("The rules popping the mode are tried {} all of its other rules", if last { "after" } else { "before" }));
+        }
+
+        let pattern = if patterns.len() == 1 { patterns[0].0.clone() } else {
+            patterns.iter().map(|(pattern, _)| format!("(?:{})", pattern)).collect::<Vec<_>>().join("|")
+        };
+        End { pattern, scope, last }
+    }
+
+    /// A rule for each scope of the keywords of a rule, matching them as whole words.
+    fn keywords(&self, spec: &RuleSpec) -> Vec<Json> {
+        let mut scopes = Vec::<(&str, Vec<String>)>::new();
+        for keyword in spec.keywords {
+            let Some(scope) = self.scopes.get(*keyword) else { continue };
+            match scopes.iter_mut().find(|(other, _)| other == scope) {
+                Some((_, words)) => words.push(regex_syntax::escape(keyword)),
+                None => scopes.push((scope, vec![regex_syntax::escape(keyword)])),
+            }
+        }
+
+        scopes.into_iter().map(|(scope, words)| {
+            let flags = if spec.case_insensitive { "i" } else { "" };
+            Json::obj(vec![
+                ("match", Json::Str(format!(r"\b(?{}:{})\b", flags, words.join("|")))),
+                ("name", Json::Str(scope.to_string())),
+            ])
+        }).collect()
+    }
+}
+
+/// The pattern of a rule in the regex syntax of TextMate, or `None` for `eof` and patterns that can not be translated.
+fn pattern(spec: &RuleSpec, warn: &mut impl FnMut(&str, String)) -> Option<String> {
+    if spec.guarded {
+        warn(&spec.source, "The guard can not be represented, so the rule is exported as if it always holds".to_string());
+    }
+    if spec.delimited {
+        warn(&spec.source, "Only the opening of a delimited pattern can be represented".to_string());
+    }
+
+    // Builtin patterns are not affected by flags
+    let (pattern, flags) = match &spec.pattern {
+        Pattern::Builtin(builtin) if *builtin == patterns::EOF => return None,
+        Pattern::Builtin(builtin) => (builtin.to_string(), String::new()),
+        Pattern::Regex(regex) => {
+            let regex = if spec.ascii_classes { ascii_classes(regex) } else { regex.clone() };
+            // Dots match newlines with `m` in TextMate
+            let flags = [(spec.case_insensitive, 'i'), (spec.dot_all, 'm')].iter().filter(|(set, _)| *set).map(|(_, flag)| *flag).collect();
+            (regex, flags)
+        }
+    };
+    let flagged = |pattern: String| if flags.is_empty() { pattern } else { format!("(?{}:{})", flags, pattern) };
+
+    let mut regex = flagged(translate(&pattern, &spec.source, warn)?);
+    if spec.bol {
+        regex = format!("^{}", regex);
+    }
+    if let Some(trail) = &spec.trail {
+        let trail = if spec.ascii_classes { ascii_classes(trail) } else { trail.clone() };
+        regex = format!("{}(?={})", regex, flagged(translate(&trail, &spec.source, warn)?));
+    }
+    Some(regex)
+}
+
+/// Translates a pattern from the syntax of the regex crate to the syntax of Oniguruma, which is used by TextMate.
+fn translate(pattern: &str, source: &str, warn: &mut impl FnMut(&str, String)) -> Option<String> {
+    let mut ast = match ast::parse::Parser::new().parse(pattern) {
+        Ok(ast) => ast,
+        Err(err) => {
+            warn(source, format!("The pattern could not be parsed: {}", err));
+            return None
+        }
+    };
+
+    let mut problems = Vec::new();
+    oniguruma_ast(&mut ast, &mut problems);
+    for problem in problems {
+        warn(source, problem.to_string());
+    }
+
+    let mut regex = String::new();
+    ast::print::Printer::new().print(&ast, &mut regex).unwrap();
+    Some(regex)
+}
+
+fn oniguruma_ast(node: &mut Ast, problems: &mut Vec<&'static str>) {
+    match node {
+        Ast::Flags(set) => {
+            oniguruma_flags(&mut set.flags, problems);
+            if set.flags.items.is_empty() {
+                *node = Ast::empty(set.span);
+            }
+        }
+        Ast::Group(group) => {
+            match &mut group.kind {
+                ast::GroupKind::NonCapturing(flags) => oniguruma_flags(flags, problems),
+                // Oniguruma names groups without the `P`
+                ast::GroupKind::CaptureName { starts_with_p, .. } => *starts_with_p = false,
+                ast::GroupKind::CaptureIndex(_) => {}
+            }
+            oniguruma_ast(&mut group.ast, problems)
+        }
+        Ast::Assertion(assertion) => match assertion.kind {
+            ast::AssertionKind::StartLine => problems.push("`^` matches at the beginning of every line in TextMate, and not only at the current position"),
+            ast::AssertionKind::EndLine | ast::AssertionKind::StartText | ast::AssertionKind::EndText
+                | ast::AssertionKind::WordBoundary | ast::AssertionKind::NotWordBoundary => {}
+            _ => problems.push(r"Only `\b` and `\B` word boundaries are supported by TextMate"),
+        },
+        Ast::ClassBracketed(class) => oniguruma_set(&mut class.kind, problems),
+        Ast::Repetition(repetition) => oniguruma_ast(&mut repetition.ast, problems),
+        Ast::Alternation(alternation) => alternation.asts.iter_mut().for_each(|ast| oniguruma_ast(ast, problems)),
+        Ast::Concat(concat) => concat.asts.iter_mut().for_each(|ast| oniguruma_ast(ast, problems)),
+        _ => {}
+    }
+}
+
+/// Rewrites the flags, as `s` is written `m` in Oniguruma, where `^` and `$` always match at lines.
+fn oniguruma_flags(flags: &mut ast::Flags, problems: &mut Vec<&'static str>) {
+    flags.items.retain_mut(|item| match item.kind {
+        FlagsItemKind::Flag(Flag::DotMatchesNewLine) => {
+            item.kind = FlagsItemKind::Flag(Flag::MultiLine);
+            true
+        }
+        FlagsItemKind::Flag(Flag::MultiLine | Flag::Unicode | Flag::CRLF) => false,
+        FlagsItemKind::Flag(Flag::SwapGreed) => {
+            problems.push("The `U` flag is not supported by TextMate");
+            false
+        }
+        _ => true,
+    });
+    if flags.items.last().is_some_and(|item| item.kind == FlagsItemKind::Negation) {
+        flags.items.pop();
+    }
+}
+
+/// Rewrites the differences of classes as intersections with the negated class, as Oniguruma only supports intersections.
+fn oniguruma_set(set: &mut ClassSet, problems: &mut Vec<&'static str>) {
+    match set {
+        ClassSet::Item(item) => oniguruma_set_item(item, problems),
+        ClassSet::BinaryOp(op) => {
+            oniguruma_set(&mut op.lhs, problems);
+            oniguruma_set(&mut op.rhs, problems);
+            match op.kind {
+                ClassSetBinaryOpKind::Intersection => {}
+                ClassSetBinaryOpKind::Difference => {
+                    let rhs = std::mem::replace(&mut *op.rhs, ClassSet::Item(ClassSetItem::Empty(op.span)));
+                    *op.rhs = ClassSet::Item(ClassSetItem::Bracketed(Box::new(ast::ClassBracketed {
+                        span: *rhs.span(),
+                        negated: true,
+                        kind: rhs,
+                    })));
+                    op.kind = ClassSetBinaryOpKind::Intersection;
+                }
+                ClassSetBinaryOpKind::SymmetricDifference => problems.push("Symmetric differences of classes are not supported by TextMate"),
+            }
+        }
+    }
+}
+
+fn oniguruma_set_item(item: &mut ClassSetItem, problems: &mut Vec<&'static str>) {
+    match item {
+        ClassSetItem::Bracketed(class) => oniguruma_set(&mut class.kind, problems),
+        ClassSetItem::Union(union) => union.items.iter_mut().for_each(|item| oniguruma_set_item(item, problems)),
+        _ => {}
+    }
+}
+
+fn captures(scope: &str) -> Json {
+    Json::Obj(vec![("0".to_string(), Json::obj(vec![("name", Json::Str(scope.to_string()))]))])
+}
+
+fn include(mode: &str) -> Json {
+    Json::obj(vec![("include", Json::Str(format!("#{}", mode)))])
+}
+
+/// The JSON of a grammar, written with the keys in order.
+enum Json {
+    Str(String),
+    Int(i64),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn obj(fields: Vec<(&str, Json)>) -> Self {
+        Json::Obj(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Json::Str(s) => write_json_string(out, s),
+            Json::Int(n) => write!(out, "{}", n).unwrap(),
+            Json::Arr(items) if items.is_empty() => out.push_str("[]"),
+            Json::Arr(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&pad);
+                    item.write(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                write!(out, "{}]", "  ".repeat(indent)).unwrap();
+            }
+            Json::Obj(fields) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(&pad);
+                    write_json_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                write!(out, "{}}}", "  ".repeat(indent)).unwrap();
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+/// Writes `s` as a JSON string, also used by the JSON output of the `lexr` binary.
+pub fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,11 @@
+/// Logs a trace message under the target "lexr", if the `log` feature is enabled.
+///
+/// The arguments are only evaluated when the message is logged.
+macro_rules! trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::trace!(target: "lexr", $($arg)*);
+    }};
+}
+
+pub(crate) use trace;
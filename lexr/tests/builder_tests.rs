@@ -0,0 +1,138 @@
+use lexr::{Action, LexError, LexerBuilder, SrcLoc};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Id(String), Num(u32), Str(String), Hash, Eof
+}
+use Token::*;
+
+#[test]
+fn rules_are_tried_in_order() {
+    let lexer = LexerBuilder::new()
+        .builtin("ws", Action::Skip)
+        .rule("if", Action::token(Hash))
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .rule("[0-9]+", Action::emit(|s, _| Num(s.parse().unwrap())))
+        .builtin("eof", Action::token(Eof))
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("if iffy 12").into_token_vec(), vec![Hash, Hash, Id("fy".to_string()), Num(12), Eof]);
+    assert_eq!(lexer.lex("ab\n c").into_vec(), vec![
+        (Id("ab".to_string()), SrcLoc::new((1, 1), (1, 2), (0, 2))),
+        (Id("c".to_string()), SrcLoc::new((2, 2), (2, 2), (4, 5))),
+        (Eof, SrcLoc::new((2, 3), (2, 3), (5, 5))),
+    ]);
+}
+
+#[test]
+fn beginning_of_line_and_flags() {
+    let lexer = LexerBuilder::new()
+        .case_insensitive()
+        .builtin("ws", Action::Skip)
+        .rule("#", Action::token(Hash)).bol()
+        .rule("[a-z#]+", Action::emit(|s, _| Id(s.to_string())))
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("#A #\n#").into_token_vec(), vec![Hash, Id("A".to_string()), Id("#".to_string()), Hash]);
+}
+
+#[test]
+fn modes_are_pushed_and_popped() {
+    let lexer = LexerBuilder::new()
+        .builtin("ws", Action::Skip)
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .rule("\"", Action::push("string"))
+        .rule("\\)", Action::Pop)
+        .mode("string")
+        .rule("[^\"]+", Action::emit(|s, _| Str(s.to_string())))
+        .rule("\"", Action::Pop)
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("a \"b c\" ) d").into_token_vec(), vec![
+        Id("a".to_string()), Str("b c".to_string()), Id("d".to_string()),
+    ]);
+}
+
+#[test]
+fn modes_are_switched() {
+    let lexer = LexerBuilder::new()
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .rule("#", Action::switch("numbers"))
+        .mode("numbers")
+        .rule("[0-9]+", Action::emit(|s, _| Num(s.parse().unwrap())))
+        .rule("#", Action::switch(LexerBuilder::<Token>::INITIAL))
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("a#12#b").into_token_vec(), vec![Id("a".to_string()), Num(12), Id("b".to_string())]);
+}
+
+#[test]
+fn trailing_context_is_not_consumed() {
+    let lexer = LexerBuilder::new()
+        .rule("[0-9]+", Action::emit(|s, _| Num(s.parse().unwrap()))).trail(r"\.\.")
+        .rule(r"[0-9]+\.[0-9]+", Action::emit(|s, _| Id(s.to_string())))
+        .rule(r"\.\.", Action::token(Hash))
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("1..2.5").into_token_vec(), vec![Num(1), Hash, Id("2.5".to_string())]);
+}
+
+#[test]
+fn break_stops_lexing() {
+    let lexer = LexerBuilder::new()
+        .rule("[a-z]", Action::emit(|s, _| Id(s.to_string())))
+        .rule(";", Action::Break)
+        .build()
+        .unwrap();
+
+    assert_eq!(lexer.lex("ab;?").into_token_vec(), vec![Id("a".to_string()), Id("b".to_string())]);
+}
+
+#[test]
+fn errors_are_returned_by_try_lex() {
+    let lexer = LexerBuilder::new()
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .build()
+        .unwrap();
+
+    let mut tokens = Vec::new();
+    let result = lexer.try_lex("ab?", |token, _| tokens.push(token));
+    assert_eq!(tokens, vec![Id("ab".to_string())]);
+    assert_eq!(result, Err(LexError::UnexpectedChar { c: '?', loc: SrcLoc::new((1, 3), (1, 3), (2, 2)) }));
+}
+
+#[test]
+#[should_panic(expected = "Unexpected character '?' at 1:3")]
+fn lexers_panic_on_unexpected_characters() {
+    let lexer = LexerBuilder::new()
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .build()
+        .unwrap();
+
+    lexer.lex("ab?").deplete();
+}
+
+#[test]
+fn invalid_rules_are_reported() {
+    let build = |builder: LexerBuilder<Token>| builder.build().err().unwrap().message;
+
+    assert!(build(LexerBuilder::new().rule("(", Action::Skip)).starts_with("Invalid pattern \"(\""));
+    assert!(build(LexerBuilder::new().rule("a*", Action::Skip)).contains("can match the empty string"));
+    assert_eq!(build(LexerBuilder::new().builtin("identifier", Action::Skip)), "Unknown builtin pattern 'identifier'");
+    assert_eq!(build(LexerBuilder::new().rule("\"", Action::push("string"))), "No mode named 'string' to switch to from \"\\\"\" in mode INITIAL");
+
+    let err = LexerBuilder::<Token>::new()
+        .rule("a", Action::Skip)
+        .mode("string")
+        .rule("b", Action::Skip)
+        .rule("(", Action::Skip)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!((err.mode.as_str(), err.rule), ("string", 1));
+}
@@ -0,0 +1,184 @@
+#![cfg(feature = "std")]
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+const SPEC: &str = r##"
+// A small language
+ws => skip
+"//[^\n]*" => skip
+r"[a-z]+" => Id
+"[0-9]+" => Int   // numbers
+"\\+" => Plus
+^"#" => Hash
+"%%" => break
+eof => Eof
+"##;
+
+fn spec_file(name: &str, spec: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, spec).unwrap();
+    path
+}
+
+fn lexr(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lexr"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn table() {
+    let spec = spec_file("table.spec", SPEC);
+    let output = lexr(&[spec.to_str().unwrap()], "a + 12 // c\n#b");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), [
+        r#"1:1          Id               "a""#,
+        r#"1:3          Plus             "+""#,
+        r#"1:5-6        Int              "12""#,
+        r##"2:1          Hash             "#""##,
+        r#"2:2          Id               "b""#,
+        r#"2:3          Eof              """#,
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn break_stops_lexing() {
+    let spec = spec_file("break.spec", SPEC);
+    let output = lexr(&[spec.to_str().unwrap()], "a %% ??");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output).lines().count(), 1);
+}
+
+#[test]
+fn json() {
+    let spec = spec_file("json.spec", SPEC);
+    let output = lexr(&["--format", "json", spec.to_str().unwrap()], "a\n\"");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), concat!(
+        r#"{"file":"<stdin>","token":"Id","lexeme":"a","loc":{"abs_range":[0,1],"start":[1,1],"end":[1,1]}}"#, "\n",
+    ));
+    assert_eq!(stderr(&output), "lexr: <stdin>: Unexpected character '\"' at 2:1\n");
+}
+
+#[test]
+fn color() {
+    let spec = spec_file("color.spec", "flags case_insensitive\nws => skip\n\"[a-z]+\" => Id\n");
+    let output = lexr(&["-f", "color", spec.to_str().unwrap()], "Ab cd\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "\x1b[96mAb\x1b[0m \x1b[96mcd\x1b[0m\n");
+}
+
+#[test]
+fn files() {
+    let spec = spec_file("files.spec", SPEC);
+    let first = spec_file("first.txt", "a");
+    let second = spec_file("second.txt", "1 ?");
+    let output = lexr(&[spec.to_str().unwrap(), first.to_str().unwrap(), second.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("first.txt <==\n1:1 "));
+    assert!(stdout(&output).contains("second.txt <==\n1:1 "));
+    assert!(stderr(&output).contains("second.txt: Unexpected character '?' at 1:3"));
+
+    let output = lexr(&[spec.to_str().unwrap(), "missing.txt"], "");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn invalid_specs() {
+    for (spec, error) in [
+        ("\"[a-z]+\" Id", "line 1: Expected `=>` after \"[a-z]+\""),
+        ("ws => skip\nidentifier => Id", "line 2: Unknown builtin pattern 'identifier'"),
+        ("flags loud", "line 1: Unknown flag 'loud'"),
+        ("\"(\" => Open", "Invalid pattern \"(\""),
+        ("\"a*\" => A", "can match the empty string"),
+        ("ws => skip\n\"(\" => Open", "line 2: Invalid pattern \"(\""),
+        ("ws => skip\nmode string\n\"a\" => A\n\"(\" => Open", "line 4: Invalid pattern \"(\""),
+    ] {
+        let path = spec_file("invalid.spec", spec);
+        let output = lexr(&[path.to_str().unwrap()], "");
+        assert_eq!(output.status.code(), Some(2), "{}", spec);
+        assert!(stderr(&output).contains(error), "{}", stderr(&output));
+    }
+
+    let output = lexr(&["--loud"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn modes_and_trailing_context() {
+    let spec = spec_file("modes.spec", r##"
+"[0-9]+" / r"\.\." => Int
+"[0-9]+(\\.[0-9]+)?" => Num
+r"\.\." => Range
+"\"" => push string
+
+mode string
+r#"[^"]+"# => Str
+"\"" => pop
+"##);
+    let output = lexr(&[spec.to_str().unwrap()], "1..2\"a b\"");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), [
+        r#"1:1          Int              "1""#,
+        r#"1:2-3        Range            "..""#,
+        r#"1:4          Num              "2""#,
+        r#"1:6-8        Str              "a b""#,
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn import() {
+    let flex = spec_file("import.l", r#"%x COMMENT
+%%
+"/*"        BEGIN(COMMENT);
+<COMMENT>{
+"*/"        BEGIN(INITIAL);
+.|\n        ;
+}
+[0-9]+      return INT;
+[ \t\n]+    /* skip */
+%%
+"#);
+    let output = lexr(&["import", flex.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(0));
+    let spec = spec_file("import.spec", &stdout(&output));
+    let output = lexr(&[spec.to_str().unwrap()], "1 /* 2 */ 3");
+    assert_eq!(stdout(&output), [
+        r#"1:1          INT              "1""#,
+        r#"1:11         INT              "3""#,
+        "",
+    ].join("\n"));
+
+    let output = lexr(&["import", "--to", "rust", "--name", "calc", flex.to_str().unwrap()], "");
+    assert!(stdout(&output).contains("lexr::lex_rule!{pub calc_comment -> () {"));
+
+    let keywords = spec_file("keywords.l", "%%\n\"if\"    return IF;\n[a-z]+  return ID;\n%%\n");
+    let output = lexr(&["import", keywords.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), format!(
+        "{}:2: warning: The rule matches \"if\", a prefix of \"ifa\", which the longest match selects the rule on line 3 for, while lexr selects this rule as it is tried first\n",
+        keywords.to_str().unwrap(),
+    ));
+
+    let bad = spec_file("bad.mll", "rule token = parse\n  | 'a' { A }\n  | 'b' { yy lexbuf }\n");
+    let output = lexr(&["import", bad.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), format!("{}:3: Could not tell the token produced by `yy lexbuf`\n", bad.to_str().unwrap()));
+}
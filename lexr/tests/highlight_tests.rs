@@ -0,0 +1,79 @@
+#![cfg(feature = "std")]
+use lexr::{lex_rule, Action, AnsiTheme, Highlighted, LexerBuilder, SrcLoc};
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    If, Id, Int, Op, Str, Eof
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r"//[^\n]*" => |_| continue,
+    "[0-9]+" => |_| Int,
+    r#""[^"]*""# => |_| Str,
+    "[<>&]" => |_| Op,
+    "[a-z]+" keywords {
+        "if" => If,
+    } => |_| Id,
+    eof => |_| Eof,
+}}
+
+fn class(token: &Token) -> Option<&'static str> {
+    match token {
+        If => Some("keyword"),
+        Int => Some("number"),
+        Str => Some("string"),
+        Op => Some("operator"),
+        Id | Eof => None,
+    }
+}
+
+#[test]
+fn html_keeps_skipped_text() {
+    let src = "if a < 10 // check \"a\" & more\n  b & \"<c>\"\n";
+    assert_eq!(lex(src).highlight(src, class).html(), concat!(
+        r#"<span class="keyword">if</span> a <span class="operator">&lt;</span> <span class="number">10</span> // check &quot;a&quot; &amp; more"#, "\n",
+        r#"  b <span class="operator">&amp;</span> <span class="string">&quot;&lt;c&gt;&quot;</span>"#, "\n",
+    ));
+}
+
+#[test]
+fn ansi_styles_each_line() {
+    let src = "if \"a\nb\" 1";
+    let theme = AnsiTheme::new()
+        .style("keyword", "1;34")
+        .style("string", "32");
+    assert_eq!(lex(src).highlight(src, class).ansi(&theme), "\x1b[1;34mif\x1b[0m \x1b[32m\"a\x1b[0m\n\x1b[32mb\"\x1b[0m 1");
+}
+
+#[test]
+fn segments_cover_the_source() {
+    let src = "x  \"é\" 42 ";
+    let highlighted = lex(src).highlight(src, class);
+    assert_eq!(highlighted.segments().collect::<Vec<_>>(), vec![
+        ("x  ", None), ("\"é\"", Some("string")), (" ", None), ("42", Some("number")), (" ", None),
+    ]);
+    assert_eq!(highlighted.segments().map(|(text, _)| text).collect::<String>(), src);
+
+    // Overlapping and empty tokens, like the ones synthesized by a layout, highlight only what no earlier token did
+    let tokens = vec![
+        (Int, SrcLoc::new((1, 1), (1, 4), (0, 3))),
+        (Str, SrcLoc::new((1, 3), (1, 6), (2, 5))),
+        (Op, SrcLoc::new((1, 6), (1, 6), (5, 5))),
+    ];
+    let highlighted = Highlighted::new("123456", tokens, class);
+    assert_eq!(highlighted.segments().collect::<Vec<_>>(), vec![("123", Some("number")), ("45", Some("string")), ("6", None)]);
+}
+
+#[test]
+fn runtime_lexers_are_highlighted() {
+    let lexer = LexerBuilder::new()
+        .builtin("ws", Action::Skip)
+        .rule("[a-z]+", Action::emit(|s, _| s.to_string()))
+        .build()
+        .unwrap();
+    let src = "if x";
+    let highlighted = lexer.lex(src).highlight(src, |token| (token == "if").then_some("keyword"));
+    assert_eq!(highlighted.html(), r#"<span class="keyword">if</span> x"#);
+}
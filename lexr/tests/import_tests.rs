@@ -0,0 +1,256 @@
+#![cfg(feature = "std")]
+use lexr::import::{ImportedAction, ImportedLexer, ImportedRule};
+use lexr::{import_flex, import_ocamllex, Action, ImportError, LexerBuilder};
+
+/// Loads an imported lexer like the `lexr` tool does with its spec, with the token names as tokens.
+fn build(imported: &ImportedLexer) -> lexr::RuntimeLexer<String> {
+    let mut builder = LexerBuilder::new();
+    if imported.case_insensitive {
+        builder = builder.case_insensitive();
+    }
+    for mode in &imported.modes {
+        builder = builder.mode(&mode.name);
+        for rule in &mode.rules {
+            let action = match &rule.action {
+                ImportedAction::Token(name) => Action::token(name.clone()),
+                ImportedAction::Skip => Action::Skip,
+                ImportedAction::Break => Action::Break,
+                ImportedAction::Push(mode) => Action::push(mode),
+                ImportedAction::Pop => Action::Pop,
+                ImportedAction::Switch(mode) => Action::switch(mode),
+            };
+            builder = match &rule.pattern {
+                Some(pattern) => builder.rule(pattern, action),
+                None => builder.builtin("eof", action),
+            };
+            if rule.bol {
+                builder = builder.bol();
+            }
+            if let Some(trail) = &rule.trail {
+                builder = builder.trail(trail);
+            }
+        }
+    }
+    builder.build().unwrap()
+}
+
+const FLEX: &str = r##"
+%{
+#include "parser.h"
+%}
+%option noyywrap caseless
+%x COMMENT
+DIGIT    [0-9]
+ID       [a-z_][a-z0-9_]*
+
+%%
+"/*"            BEGIN(COMMENT);
+<COMMENT>{
+  "*/"          BEGIN(INITIAL);
+  [^*\n]+       /* skip */
+  "*"|\n        ;
+}
+{DIGIT}+/".."   return INT;
+{DIGIT}+"."{DIGIT}*  { yylval.f = atof(yytext);
+                       return FLOAT; }
+{DIGIT}+        return INT;
+".."            return RANGE;
+"if"            |
+"else"          return KEYWORD;
+{ID}            return ID;
+^"#"[a-z]+$     return DIRECTIVE;
+[ \t\n]+        /* whitespace */
+<<EOF>>         return 0;
+%%
+int main() {}
+"##;
+
+#[test]
+fn flex_specs_are_imported() {
+    let imported = import_flex(FLEX).unwrap();
+    assert!(imported.case_insensitive);
+    assert_eq!(imported.modes.iter().map(|mode| mode.name.as_str()).collect::<Vec<_>>(), vec!["INITIAL", "COMMENT"]);
+    assert_eq!(imported.modes[0].rules[1], ImportedRule {
+        line: 17,
+        pattern: Some("[0-9]+".to_string()),
+        trail: Some(r"\.\.".to_string()),
+        bol: false,
+        action: ImportedAction::Token("INT".to_string()),
+    });
+    assert_eq!(imported.modes[0].rules[8], ImportedRule {
+        line: 25,
+        pattern: Some(r"\#[a-z]+".to_string()),
+        trail: Some(r"\n".to_string()),
+        bol: true,
+        action: ImportedAction::Token("DIRECTIVE".to_string()),
+    });
+
+    assert_eq!(imported.to_spec(), r##"flags case_insensitive
+r"/\*" => push COMMENT
+r"[0-9]+" / r"\.\." => INT
+r"[0-9]+\.[0-9]*" => FLOAT
+r"[0-9]+" => INT
+r"\.\." => RANGE
+r"if" => KEYWORD
+r"else" => KEYWORD
+r"(?:[a-z_][a-z0-9_]*)" => ID
+^ r"\#[a-z]+" / r"\n" => DIRECTIVE
+r"[ \t\n]+" => skip
+eof => break
+
+mode COMMENT
+r"\*/" => pop
+r"[^\*\n]+" => skip
+r"\*|\n" => skip
+"##);
+
+    let lexer = build(&imported);
+    assert_eq!(lexer.lex("1..2.5 /* x * y */ IF\n#define\n").into_token_vec(), vec!["INT", "RANGE", "FLOAT", "KEYWORD", "DIRECTIVE"]);
+}
+
+const OCAMLLEX: &str = r#"
+{
+open Parser
+exception Eof
+}
+let digit = ['0'-'9']
+let ident = ['a'-'z' '_'] (['a'-'z' '0'-'9' '_'])*
+let blank = [' ' '\t' '\r'] # '\r'
+rule token = parse
+  | blank+            { token lexbuf }
+  | '\n'              { Lexing.new_line lexbuf; token lexbuf }
+  | "(*"              { comment lexbuf; token lexbuf }   (* nested comments are not *)
+  | digit+ as n       { INT (int_of_string n) }
+  | ident as id       { Parser.IDENT id }
+  | '+' | '-'         { OP }
+  | eof               { raise End_of_file }
+  | _                 { raise (Error "unexpected") }
+and comment = parse
+  | "*)"              { () }
+  | _                 { comment lexbuf }
+{
+let () = ()
+}
+"#;
+
+#[test]
+fn ocamllex_entry_points_become_modes() {
+    let imported = import_ocamllex(OCAMLLEX).unwrap();
+    assert_eq!(imported.to_spec(), r#"r"[[ \t\r]--[\r]]+" => skip
+r"\n" => skip
+r"\(\*" => push comment
+r"[0-9]+" => INT
+r"[a-z_][a-z0-9_]*" => IDENT
+r"\+|\-" => OP
+eof => break
+
+mode comment
+r"\*\)" => pop
+r"(?s:.)" => skip
+"#);
+
+    let lexer = build(&imported);
+    assert_eq!(lexer.lex("a + (* 1 *) 2\n- b").into_token_vec(), vec!["IDENT", "OP", "INT", "OP", "IDENT"]);
+}
+
+#[test]
+fn lex_rules_are_emitted() {
+    let imported = import_ocamllex(OCAMLLEX).unwrap();
+    assert_eq!(imported.to_lex_rule("calc").unwrap(), r##"#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    INT,
+    IDENT,
+    OP,
+}
+
+lexr::lex_rule!{pub calc -> Token {
+    r"[[ \t\r]--[\r]]+" => |_| continue,
+    r"\n" => |_| continue,
+    r"\(\*" => |_, buf| { calc_comment(buf).deplete(); continue },
+    r"[0-9]+" => |_| Token::INT,
+    r"[a-z_][a-z0-9_]*" => |_| Token::IDENT,
+    r"\+|\-" => |_| Token::OP,
+    eof => |_| break,
+}}
+
+lexr::lex_rule!{pub calc_comment -> () {
+    r"\*\)" => |_| break,
+    r"(?s:.)" => |_| continue,
+}}
+"##);
+
+    let strings = import_flex("%x STR\n%%\n\\\" BEGIN(STR);\n<STR>[^\"]+ return STR;\n").unwrap();
+    assert_eq!(strings.to_lex_rule("lex"), Err(vec![ImportError {
+        line: 4,
+        message: "Mode STR produces tokens, which a sub rule can not return".to_string(),
+    }]));
+}
+
+// The emitted source of lex_rules_are_emitted, which has to compile
+mod emitted {
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Token {
+        INT,
+        IDENT,
+        OP,
+    }
+
+    lexr::lex_rule!{pub calc -> Token {
+        r"[[ \t\r]--[\r]]+" => |_| continue,
+        r"\n" => |_| continue,
+        r"\(\*" => |_, buf| { calc_comment(buf).deplete(); continue },
+        r"[0-9]+" => |_| Token::INT,
+        r"[a-z_][a-z0-9_]*" => |_| Token::IDENT,
+        r"\+|\-" => |_| Token::OP,
+        eof => |_| break,
+    }}
+
+    lexr::lex_rule!{pub calc_comment -> () {
+        r"\*\)" => |_| break,
+        r"(?s:.)" => |_| continue,
+    }}
+}
+
+#[test]
+fn emitted_lex_rules_compile() {
+    use emitted::Token::*;
+    assert_eq!(emitted::calc("a (* b *) 1").into_token_vec(), vec![IDENT, INT]);
+}
+
+#[test]
+fn unsupported_constructs_are_reported() {
+    let errors = |result: Result<ImportedLexer, Vec<ImportError>>| result.unwrap_err().into_iter()
+        .map(|err| err.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(errors(import_flex("%x A\n%array\n%prefix foo\n%%\n<B>\"a\" REJECT;\n[a-z]{-}[b] ;\n\"c\" { BEGIN(A); return C; }\n'x' return 'x';\n{NUM} ;\n")), vec![
+        "line 3: Unsupported directive %prefix",
+        "line 5: REJECT is not supported",
+        "line 5: Undeclared start condition B",
+        "line 6: The class operator {-} is not supported",
+        "line 7: Actions that do more than returning a token or changing the start condition are not supported: `{ BEGIN(A); return C; }`",
+        "line 8: Returning character literals as tokens is not supported",
+        "line 9: Undefined definition {NUM}",
+    ]);
+    assert_eq!(errors(import_flex("a [a-z]\n")), vec!["line 1: Expected `%%` to start the rules section"]);
+
+    assert_eq!(errors(import_ocamllex("rule token = shortest\n  | 'a' { A }\n")), vec!["line 1: Shortest match is not supported"]);
+    assert_eq!(errors(import_ocamllex("rule token = parse\n  | 'a' eof { A }\n")), vec!["line 2: eof can only be used as a whole pattern"]);
+    assert_eq!(errors(import_ocamllex("rule token = parse\n  | 'a' { A }\n  | '\"' { string lexbuf }\nand string = parse\n  | [^ '\"']+ { STR }\n")), vec![
+        "line 3: Calling the entry point string, which returns tokens, is not supported",
+    ]);
+}
+
+#[test]
+fn longest_matches_are_warned_about() {
+    let imported = import_flex("%%\n\"if\"    return IF;\n[a-z]+  return ID;\n%%\n").unwrap();
+    assert_eq!(imported.warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>(), vec![
+        r#"line 2: The rule matches "if", a prefix of "ifa", which the longest match selects the rule on line 3 for, while lexr selects this rule as it is tried first"#,
+    ]);
+
+    assert!(import_flex("%%\n[a-z]+  return ID;\n[0-9]+  return INT;\n%%\n").unwrap().warnings.is_empty());
+    assert_eq!(import_flex(FLEX).unwrap().warnings.iter().map(|warning| warning.line).collect::<Vec<_>>(), vec![22, 23]);
+    assert_eq!(import_ocamllex(OCAMLLEX).unwrap().warnings, vec![]);
+}
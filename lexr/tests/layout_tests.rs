@@ -0,0 +1,98 @@
+use lexr::{lex_rule, Layout, LexError, SrcLoc, TabPolicy};
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id, Comment, Indent, Dedent, Newline, Eof
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    r"[ \t\n]" => |_| continue,
+    r"#[^\n]*" => |_| continue,
+    r"[a-z]+" => |_| Id,
+    eof => |_| Eof,
+}}
+
+fn layout(src: &str, tabs: TabPolicy) -> Result<Vec<Token>, LexError> {
+    lex(src)
+        .layout(Layout::new(|| Indent, || Dedent, || Newline).tabs(tabs))
+        .map(|res| res.map(|(t, _)| t))
+        .collect()
+}
+
+#[test]
+fn indent_and_dedent_are_synthesized() {
+    let tokens = layout("a\n  b\n    c\n  d\ne", TabPolicy::Strict).unwrap();
+    assert_eq!(tokens, vec![
+        Id, Newline,
+        Indent, Id, Newline,
+        Indent, Id, Newline,
+        Dedent, Id, Newline,
+        Dedent, Id, Newline,
+        Eof
+    ]);
+}
+
+#[test]
+fn blocks_are_closed_at_end_without_eof_rule() {
+    lex_rule!{no_eof -> Token {
+        r"[ \n]" => |_| continue,
+        r"[a-z]+" => |_| Id,
+    }}
+
+    let src = "a\n  b\n    c";
+    let tokens = no_eof(src)
+        .layout(Layout::new(|| Indent, || Dedent, || Newline))
+        .map(|res| res.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, vec![Id, Newline, Indent, Id, Newline, Indent, Id, Newline, Dedent, Dedent]);
+}
+
+#[test]
+fn blank_and_comment_lines_are_ignored() {
+    let tokens = layout("a\n\n      # comment\n  b\n\n# comment\n  c", TabPolicy::Strict).unwrap();
+    assert_eq!(tokens, vec![Id, Newline, Indent, Id, Newline, Id, Newline, Dedent, Eof]);
+}
+
+#[test]
+fn ignored_tokens_do_not_affect_layout() {
+    lex_rule!{with_comments -> Token {
+        r"[ \n]" => |_| continue,
+        r"#[^\n]*" => |_| Comment,
+        r"[a-z]+" => |_| Id,
+    }}
+
+    let src = "a\n# comment\n  b";
+    let tokens = with_comments(src)
+        .layout(Layout::new(|| Indent, || Dedent, || Newline).ignore(|t| *t == Comment))
+        .map(|res| res.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, vec![Id, Comment, Newline, Indent, Id, Newline, Dedent]);
+}
+
+#[test]
+fn inconsistent_dedent_is_an_error() {
+    let err = layout("a\n    b\n  c", TabPolicy::Strict).unwrap_err();
+    assert_eq!(err, LexError::InconsistentDedent { loc: SrcLoc::new((3, 3), (3, 3), (10, 10)) });
+    assert_eq!(err.to_string(), "Dedent does not match any outer indentation level at 3:3");
+}
+
+#[test]
+fn tab_policies() {
+    let src = "a\n\tb\n        c";
+
+    assert_eq!(layout(src, TabPolicy::Expand(8)).unwrap(), vec![Id, Newline, Indent, Id, Newline, Id, Newline, Dedent, Eof]);
+    assert_eq!(layout(src, TabPolicy::Expand(4)).unwrap(), vec![Id, Newline, Indent, Id, Newline, Indent, Id, Newline, Dedent, Dedent, Eof]);
+    assert!(matches!(layout(src, TabPolicy::Strict), Err(LexError::MixedIndentation { .. })));
+    assert!(matches!(layout(src, TabPolicy::Forbid), Err(LexError::MixedIndentation { .. })));
+    assert!(layout("a\n\tb\n\t  c\n\td", TabPolicy::Strict).is_ok());
+}
+
+#[test]
+fn synthesized_tokens_have_locations() {
+    let src = "a\n  b\n";
+    let locs = lex(src)
+        .layout(Layout::new(|| Indent, || Dedent, || Newline))
+        .map(|res| res.unwrap().1.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(locs, vec!["1:1", "1:2", "2:3", "2:3", "2:4", "3:1", "3:1"]);
+}
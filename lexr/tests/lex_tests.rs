@@ -221,4 +221,484 @@ fn readme_example() {
     
     let tokens = lex("a ba ca S 42").into_token_vec();
     assert_eq!(tokens, vec![A, B, C, D, Num, Eof])
-}
\ No newline at end of file
+}
+
+#[test]
+fn abs_loc_accounts_for_skipped_input() {
+    lex_rule!{lex -> Token {
+        ws => |_| continue,
+        "a" => |_| A,
+    }}
+
+    let locs = lex("a  a\na").map(|(_, loc)| loc.get_abs_loc()).collect::<Vec<_>>();
+    assert_eq!(locs, vec![(0, 1), (3, 4), (5, 6)]);
+}
+
+#[test]
+fn delimited_literals_match_their_fence() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        Raw(String), Heredoc(String), Long(String), Id
+    }
+
+    lex_rule!{lex -> T {
+        ws => |_| continue,
+        r##"r#*""## until |open| format!("\"{}", &open[1..open.len() - 1]) => |body| T::Raw(body.to_string()),
+        r"<<[A-Z]+\n" until |open| format!("\n{}", open[2..].trim_end()) => |body| T::Heredoc(body.to_string()),
+        r"\[=*\[" until |open| open.replace('[', "]") => |body| T::Long(body.to_string()),
+        r"[a-z]+" => |_| T::Id,
+    }}
+
+    let tokens = lex(r###"r#"a "b" c"# r"" <<EOF
+x
+EOF [==[ ]] ]=] ]==] a"###).into_token_vec();
+    assert_eq!(tokens, vec![
+        T::Raw(r#"a "b" c"#.to_string()),
+        T::Raw("".to_string()),
+        T::Heredoc("x".to_string()),
+        T::Long(" ]] ]=] ".to_string()),
+        T::Id
+    ]);
+}
+
+#[test]
+fn delimited_literals_track_location() {
+    lex_rule!{lex -> Token {
+        ws => |_| continue,
+        "<<" until |_| ">>" => |_| A,
+        "b" => |_| B,
+    }}
+
+    let tokens = lex("<<x\ny>>\n  <<é\n\n>> b").map(|(t, loc)| (t, loc.to_string())).collect::<Vec<_>>();
+    assert_eq!(tokens, vec![(A, "1:1-2:3".to_string()), (A, "3:3-5:2".to_string()), (B, "5:4".to_string())]);
+}
+
+#[test]
+#[should_panic(expected = "Unterminated literal starting at 1:2-3, expected ']]'")]
+fn unterminated_delimited_literal() {
+    lex_rule!{lex -> Token {
+        "a" => |_| A,
+        r"\[\[" until |_| "]]" => |_| B,
+    }}
+
+    lex("a[[ b ]").deplete();
+}
+
+#[test]
+fn scan_until_from_action() {
+    lex_rule!{lex -> Token {
+        "/\\*" => |_, buf| match buf.scan_until("*/") {
+            Ok(_) => continue,
+            Err(_) => break,
+        },
+        "a" => |_| A,
+    }}
+
+    assert_eq!(lex("a/* a */a/* a").into_token_vec(), vec![A, A]);
+}
+
+#[test]
+fn captures_are_available_to_actions() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        Hex(u32), Str(String), Pair(String, String)
+    }
+
+    lex_rule!{lex -> T {
+        ws => |_| continue,
+        r"0x([0-9a-f]+)" => |_, _, _, caps| T::Hex(u32::from_str_radix(caps.get(1).unwrap(), 16).unwrap()),
+        r#""([^"]*)""# => |_, _, _, caps| T::Str(caps[1].to_string()),
+        r"(?<key>[a-z]+)=(?<value>[a-z]*)" => |_, _, _, caps| T::Pair(caps["key"].to_string(), caps.name("value").unwrap().to_string()),
+    }}
+
+    let tokens = lex(r#"0x1f "abc" key=value"#).into_token_vec();
+    assert_eq!(tokens, vec![T::Hex(31), T::Str("abc".to_string()), T::Pair("key".to_string(), "value".to_string())]);
+}
+
+#[test]
+fn captures_have_locations() {
+    lex_rule!{lex -> Option<lexr::SrcLoc> {
+        ws => |_| continue,
+        r"[0-9]+([a-z])?" => |_, _, _, caps| caps.loc(1),
+        r"<(\n*)(x)>" => |_, _, _, caps| { assert_eq!(caps.loc(1).unwrap().get_abs_loc(), (7, 9)); caps.loc(2) },
+    }}
+
+    let locs = lex("12a 3 <\n\nx>").map(|(loc, _)| loc.map(|l| (l.to_string(), l.get_abs_loc()))).collect::<Vec<_>>();
+    assert_eq!(locs, vec![
+        Some(("1:3".to_string(), (2, 3))),
+        None,
+        Some(("3:1".to_string(), (9, 10))),
+    ]);
+}
+
+#[test]
+fn trailing_context_is_not_consumed() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        Int(String), Float(String), Range, Call(String), Id(String)
+    }
+
+    lex_rule!{lex -> T {
+        ws => |_| continue,
+        "[0-9]+" / r"\.\." => |s| T::Int(s.to_string()),
+        r"[0-9]+\.[0-9]+" => |s| T::Float(s.to_string()),
+        "[0-9]+" => |s| T::Int(s.to_string()),
+        r"\.\." => |_| T::Range,
+        "[a-z]+" / r"\s*" r"\(" => |s| T::Call(s.to_string()),
+        "[a-z]+" => |s| T::Id(s.to_string()),
+        r"[()]" => |_| continue,
+    }}
+
+    let tokens = lex("1..2 1.5 f (x) y").into_token_vec();
+    assert_eq!(tokens, vec![
+        T::Int("1".to_string()), T::Range, T::Int("2".to_string()),
+        T::Float("1.5".to_string()),
+        T::Call("f".to_string()), T::Id("x".to_string()), T::Id("y".to_string()),
+    ]);
+}
+
+#[test]
+fn trailing_context_is_excluded_from_loc() {
+    lex_rule!{lex -> Token {
+        "a+" / "b" => |_| A,
+        "b" => |_| B,
+    }}
+
+    let locs = lex("aab").map(|(t, loc)| (t, loc.get_abs_loc())).collect::<Vec<_>>();
+    assert_eq!(locs, vec![(A, (0, 2)), (B, (2, 3))]);
+}
+
+#[test]
+fn trailing_context_is_excluded_from_captures() {
+    lex_rule!{lex -> Token {
+        "[0-9]+" / r"(?<dots>\.\.)" => |_, _, _, caps| {
+            assert_eq!((caps.get(0), caps.loc(0).map(|loc| loc.get_abs_loc())), (Some("12"), Some((0, 2))));
+            assert_eq!((caps.len(), caps.get(1), caps.name("dots"), caps.name("__lexr_trail")), (1, None, None, None));
+            A
+        },
+        r"\.\." => |_| B,
+    }}
+
+    assert_eq!(lex("12..").into_token_vec(), vec![A, B]);
+}
+
+#[test]
+fn alternations_are_anchored() {
+    lex_rule!{lex -> Token {
+        "a|b" => |_| A,
+        _ => |_| C,
+    }}
+
+    let tokens = lex("cb").into_token_vec();
+    assert_eq!(tokens, vec![C, A]);
+}
+
+#[test]
+fn bol_rules_only_match_at_line_start() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        Directive(String), Heading, Hash, Word
+    }
+
+    lex_rule!{lex -> T {
+        ws => |_| continue,
+        ^ "#" "[a-z]+" => |s| T::Directive(s.to_string()),
+        ^ "#+" / " " => |_| T::Heading,
+        "#" => |_| T::Hash,
+        "[a-z]+" => |_, buf| { assert!(!buf.at_line_start()); T::Word },
+    }}
+
+    let tokens = lex("#define x #y\n## a #\n #z").into_token_vec();
+    assert_eq!(tokens, vec![
+        T::Directive("#define".to_string()), T::Word, T::Hash, T::Word,
+        T::Heading, T::Word, T::Hash,
+        T::Hash, T::Word,
+    ]);
+}
+
+#[test]
+fn guards_fall_through_to_next_rule() {
+    lex_rule!{lex(strict: bool) -> Token {
+        ws => |_| continue,
+        "a" if strict => |_| A,
+        "a" => |_| B,
+        "[a-z]" if false => |_| A,
+        _ => |_| C,
+    }}
+
+    assert_eq!(lex("a b", true).into_token_vec(), vec![A, C]);
+    assert_eq!(lex("a b", false).into_token_vec(), vec![B, C]);
+}
+
+#[test]
+fn guards_can_use_state_and_other_pattern_forms() {
+    use std::cell::Cell;
+
+    lex_rule!{lex<'a>(depth: &'a Cell<usize>) -> Token {
+        ws => |_| continue,
+        r"\(" => |_| { depth.set(depth.get() + 1); continue },
+        r"\)" => |_| { depth.set(depth.get() - 1); continue },
+        ^ "a" if depth.get() == 0 => |_| A,
+        "a" / "b" if depth.get() > 0 => |_| B,
+        "<" until |_| ">" if depth.get() > 1 => |_| C,
+        "[a-z<>]" => |_| continue,
+    }}
+
+    let depth = Cell::new(0);
+    let tokens = lex("a (ab a <x> ((<y>))) a\na", &depth).into_token_vec();
+    assert_eq!(tokens, vec![A, B, C, A]);
+}
+
+#[test]
+fn previous_token_is_available_to_guards_and_actions() {
+    #[derive(Debug, PartialEq, Clone)]
+    enum T {
+        Id, Num, Div, Regex(String), Minus, Neg
+    }
+
+    fn is_operand(t: &T) -> bool {
+        matches!(t, T::Id | T::Num | T::Regex(_))
+    }
+
+    lex_rule!{lex -> T, prev: bool = is_operand {
+        ws => |_| continue,
+        "[a-z]+" => |_| T::Id,
+        "[0-9]+" => |_| T::Num,
+        "/" if prev == Some(true) => |_| T::Div,
+        "/[^/]*/" => |s| T::Regex(s.to_string()),
+        "-" => |_| if prev == Some(true) { T::Minus } else { T::Neg },
+    }}
+
+    let tokens = lex("-a / b / /ab+/ - -2").into_token_vec();
+    assert_eq!(tokens, vec![T::Neg, T::Id, T::Div, T::Id, T::Div, T::Regex("/ab+/".to_string()), T::Minus, T::Neg, T::Num]);
+
+    lex_rule!{cloned -> T, prev: T = T::clone {
+        "a" => |_| T::Id,
+        "b" => |_| if prev == Some(T::Id) { T::Num } else { T::Neg },
+    }}
+
+    assert_eq!(cloned("bab").into_token_vec(), vec![T::Neg, T::Id, T::Num]);
+}
+
+#[test]
+fn priorities_override_declaration_order() {
+    lex_rule!{lex -> Token {
+        ws => |_| continue,
+        "[a-z]+" => |_| A,
+        #[priority(10)] "if" => |_| B,
+        #[priority(10)] "i[a-z]" => |_| C,
+        #[priority(-1)] "[a-z]+|[0-9]" => |_| Eof,
+        "[0-9]" => |_| A,
+    }}
+
+    let tokens = lex("if is x ifx 1").into_token_vec();
+    assert_eq!(tokens, vec![B, C, A, B, A, A]);
+}
+
+mod sets {
+    use lexr::lex_rule_set;
+
+    lex_rule_set!{pub(crate) common {
+        ws => |_| continue,
+        #[name(word)] "[a-z]+" => |_| A,
+        #[name(digit)] "[0-9]" => |_| C,
+    }}
+}
+
+#[test]
+fn rule_sets_can_be_included_and_extended() {
+    use lexr::lex_rule_set;
+
+    lex_rule!{base -> Token {
+        include sets::common,
+        eof => |_| Eof,
+    }}
+
+    assert_eq!(base("ab 1").into_token_vec(), vec![A, C, Eof]);
+
+    lex_rule!{dialect -> Token {
+        #[before(word)] "if" => |_| B,
+        include crate::sets::common,
+        #[name(digit)] "[0-9]+" => |_| Eof,
+        #[after(word)] "[0-9]+[a-z]" => |_| C,
+    }}
+
+    assert_eq!(dialect("if ab 12 12x").into_token_vec(), vec![B, A, Eof, C]);
+
+    lex_rule_set!{extended {
+        include sets::common,
+        #[before(word)] "if" => |_| B,
+    }}
+
+    lex_rule!{nested -> Token {
+        include extended,
+    }}
+
+    assert_eq!(nested("if ab 1").into_token_vec(), vec![B, A, C]);
+}
+
+#[test]
+fn pattern_flags_apply_to_concatenated_patterns() {
+    const SEL: &str = "sel";
+    const ECT: &str = "ect";
+
+    lex_rule!{rule_flags -> Token {
+        ws => |_| continue,
+        #[case_insensitive] SEL ECT => |_| A,
+        #[ascii_classes] r"\w+\b" => |_| B,
+        #[dot_all] "<.*>" => |_| C,
+        _ => |_| Eof,
+    }}
+
+    let tokens = rule_flags("SeLeCt select ab_1 é <a\nb>").into_token_vec();
+    assert_eq!(tokens, vec![A, A, B, Eof, C]);
+
+    lex_rule!{#[case_insensitive] #[ascii_classes] lexer_flags -> Token {
+        ws => |_| continue,
+        "select|from" => |_| A,
+        r"\w+" => |_| B,
+        _ => |_| Eof,
+    }}
+
+    let tokens = lexer_flags("SELECT x FROM éx").into_token_vec();
+    assert_eq!(tokens, vec![A, B, A, Eof, B]);
+}
+
+#[test]
+fn keywords_are_looked_up_after_matching_identifiers() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        In, If, Else, Id(String), Num
+    }
+
+    lex_rule!{lex -> T {
+        ws => |_| continue,
+        "[a-zA-Z]+" keywords {
+            "in" => T::In,
+            "if" => T::If,
+            "else" => T::Else,
+        } => |s| T::Id(s.to_string()),
+        "[0-9]+" => |_| T::Num,
+    }}
+
+    let tokens = lex("if input in else 1 IF").into_token_vec();
+    assert_eq!(tokens, vec![T::If, T::Id("input".to_string()), T::In, T::Else, T::Num, T::Id("IF".to_string())]);
+
+    lex_rule!{#[case_insensitive] insensitive -> T {
+        ws => |_| continue,
+        "[a-z]+" / "[0-9]" keywords { "if" => T::If } if true => |s| T::Id(s.to_string()),
+        "[a-z]+" keywords { "if" => T::If, "else" => T::Else } => |s| T::Id(s.to_string()),
+        "[0-9]" => |_| T::Num,
+    }}
+
+    let tokens = insensitive("If1 ELSE x").into_token_vec();
+    assert_eq!(tokens, vec![T::If, T::Num, T::Else, T::Id("x".to_string())]);
+}
+
+#[test]
+fn keyword_table_finds_every_keyword() {
+    let words = (0..1000).map(|i| format!("kw{}", i)).collect::<Vec<_>>();
+    let words = words.iter().map(|w| w.as_str()).collect::<Vec<_>>();
+    let table = lexr::KeywordTable::new(&words, false);
+
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(table.get(word), Some(i));
+    }
+    assert_eq!(table.get("kw1000"), None);
+    assert_eq!(table.get("KW1"), None);
+    assert_eq!(lexr::KeywordTable::new(&[], false).get(""), None);
+}
+
+#[test]
+fn builtin_patterns() {
+    #[derive(Debug, PartialEq)]
+    enum T {
+        Nl, Id(String), Ascii(String), Int, Float, Str(String), Other
+    }
+
+    lex_rule!{lex -> T {
+        newline => |_| T::Nl,
+        ws => |_| continue,
+        #[priority(1)] "#" lexr::patterns::ASCII_IDENT => |s| T::Ascii(s.to_string()),
+        xid_ident => |s| T::Id(s.to_string()),
+        float => |_| T::Float,
+        int => |_| T::Int,
+        string => |s| T::Str(s.to_string()),
+        _ => |_| T::Other,
+    }}
+
+    let tokens = lex("été\u{a0}_x1\r\n\r1.5e3 2e-1 3.0 42\u{2028}\"a\\\"b\" #ab1é").into_token_vec();
+    assert_eq!(tokens, vec![
+        T::Id("été".to_string()), T::Id("_x1".to_string()), T::Nl, T::Nl,
+        T::Float, T::Float, T::Float, T::Int, T::Nl,
+        T::Str("\"a\\\"b\"".to_string()), T::Ascii("#ab1".to_string()), T::Id("é".to_string()),
+    ]);
+
+    lex_rule!{ascii -> T {
+        ascii_ident => |s| T::Ascii(s.to_string()),
+        _ => |_| T::Other,
+    }}
+
+    assert_eq!(ascii("a_1é").into_token_vec(), vec![T::Ascii("a_1".to_string()), T::Other]);
+}
+
+#[test]
+fn unreachable_rules_are_reported_with_a_witness() {
+    lex_rule!{lex -> Token {
+        "[a-z]+" => |_| A,
+        "if" => |_| B,
+        "a" => |_| A,
+        "ab" => |_| B,
+        "x\\b" => |_| A,
+        "0" => |_| C,
+        "0\\b" => |_| C,
+        r"1\b" => |_| C,
+        "1" => |_| C,
+        #[priority(1)] "in" => |_| B,
+        "=" if false => |_| A,
+        "=" => |_| B,
+        ^ "#" => |_| A,
+        "#" => |_| B,
+        "[^\\s\\S]" => |_| C,
+        _ => |_| Eof,
+        "[0-9]" => |_| A,
+        eof => |_| Eof,
+    }}
+
+    let unreachable = lex("").unreachable_rules();
+    let found = unreachable.iter()
+        .map(|u| (u.rule.source.as_str(), u.witness.as_deref(), u.shadowed_by.as_ref().map(|r| r.source.as_str())))
+        .collect::<Vec<_>>();
+
+    assert_eq!(found, vec![
+        ("\"if\"", Some("if"), Some("\"[a-z]+\"")),
+        ("\"a\"", Some("a"), Some("\"[a-z]+\"")),
+        ("\"ab\"", Some("ab"), Some("\"[a-z]+\"")),
+        ("\"[^\\\\s\\\\S]\"", None, None),
+        ("\"[0-9]\"", Some("0"), Some("\"0\"")),
+    ]);
+
+    // Unicode word boundaries can not be fully analyzed, so those rules are assumed reachable
+    assert_eq!(unreachable[0].rule.index, 1);
+    assert_eq!(lex("").rules()[0].source, "\"in\"");
+}
+
+#[test]
+#[should_panic(expected = "can match the empty string")]
+fn nullable_patterns_are_rejected() {
+    lex_rule!{lex -> Token {
+        "a" => |_| A,
+        "[0-9]*" / "x" => |_| B,
+    }}
+
+    let _ = lex("a");
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn regex_and_lazy_static_are_reexported() {
+    lexr::lazy_static::lazy_static! {
+        static ref WORD: lexr::regex::Regex = lexr::regex::Regex::new("[a-z]+").unwrap();
+    }
+    assert_eq!(WORD.find("12 ab").map(|mat| mat.as_str()), Some("ab"));
+}
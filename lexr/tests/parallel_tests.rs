@@ -0,0 +1,86 @@
+#![cfg(feature = "std")]
+use lexr::{lex_rule, Parallel, SrcLoc};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Id(String), Int(u32), Str(String), Comma, Newline, Comment, Eof,
+}
+use Token::*;
+
+lex_rule!{comment -> () {
+    r"\*/" => |_| break,
+    r"(?s:.)" => |_| continue,
+}}
+
+lex_rule!{lex -> Token {
+    " " => |_| continue,
+    "\n" => |_| Newline,
+    r"/\*" => |_, buf| { comment(buf).deplete(); Comment },
+    "," => |_| Comma,
+    "[0-9]+" => |s| Int(s.parse().unwrap()),
+    "\"" until |_| "\"" => |s| Str(s.to_string()),
+    r"\w+" => |s| Id(s.to_string()),
+    eof => |_| Eof,
+}}
+
+const SRC: &str = "id,name,note\n1,é,\"a\nb\"\n2, ø ,/* multi\nline */\n\n3,x,\"%\n\"\n4,y,z";
+
+#[test]
+fn chunks_are_stitched_like_sequential_lexing() {
+    let expected = lex(SRC).into_vec();
+    assert_eq!(expected.last(), Some(&(Eof, SrcLoc::new((9, 6), (9, 6), (63, 63)))));
+
+    // The newlines inside the string and the comment are split at, and lexed again from where the lexemes end
+    for chunk_size in 1..SRC.len() + 1 {
+        for threads in [1, 3] {
+            let parallel = Parallel::lines().chunk_size(chunk_size).threads(threads);
+            assert_eq!(parallel.lex(SRC, || lex("")), expected, "chunk size {}", chunk_size);
+        }
+    }
+}
+
+#[test]
+fn predicates_decide_the_chunks() {
+    let src = "a,b,c,\"d,e\",f";
+    let expected = lex(src).into_vec();
+    let parallel = Parallel::new(|src, idx| src.as_bytes()[idx - 1] == b',').chunk_size(1);
+    assert_eq!(parallel.lex(src, || lex("")), expected);
+
+    // Chunks starting in the middle of a line know their column
+    let parallel = Parallel::new(|_, _| true).chunk_size(2).threads(2);
+    assert_eq!(parallel.lex(SRC, || lex("")), lex(SRC).into_vec());
+    assert_eq!(parallel.lex("", || lex("")), vec![(Eof, SrcLoc::new((1, 1), (1, 1), (0, 0)))]);
+}
+
+#[test]
+fn lexing_ends_at_break() {
+    lex_rule!{until_dot -> Token {
+        "[a-z]+" => |s| Id(s.to_string()),
+        "\n" => |_| continue,
+        r"\." => |_| break,
+    }}
+    let src = "a\nb\n.\nc\nd";
+    assert_eq!(Parallel::lines().chunk_size(1).lex(src, || until_dot("")), until_dot(src).into_vec());
+}
+
+#[test]
+#[should_panic(expected = "Unexpected character '%' at 3:2")]
+fn unexpected_characters_panic() {
+    Parallel::lines().chunk_size(1).lex("1\n2\n3%\n4", || lex(""));
+}
+
+#[test]
+fn lexers_keeping_state_are_lexed_in_one_go() {
+    lex_rule!{keys -> Token, prev: bool = is_newline {
+        " " => |_| continue,
+        "\n" => |_| Newline,
+        "[a-z]+" if prev == Some(true) => |s| Id(s.to_uppercase()),
+        "[a-z]+" => |s| Id(s.to_string()),
+    }}
+    fn is_newline(token: &Token) -> bool {
+        *token == Newline
+    }
+
+    let src = "a b\nc d\ne";
+    assert_eq!(Parallel::lines().chunk_size(1).threads(2).lex(src, || keys("")), keys(src).into_vec());
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "serde")]
+use lexr::{lex_rule, serial, LexError, SrcLoc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Token {
+    Id(String), Num(u32), Plus,
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    "[a-z]+" => |s| Id(s.to_string()),
+    "[0-9]+" => |s| Num(s.parse().unwrap()),
+    r"\+" => |_| Plus,
+}}
+
+#[test]
+fn json_lines_round_trip() {
+    let mut out = Vec::new();
+    lex("a + 12\nb").write_json_lines(&mut out).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), 4);
+    assert_eq!(text.lines().next().unwrap(), r#"[{"Id":"a"},{"abs_range":[0,1],"start":[1,1],"end":[1,1]}]"#);
+
+    let tokens = serial::read_json_lines::<Token>(text.as_bytes()).unwrap();
+    assert_eq!(tokens, lex("a + 12\nb").into_vec());
+}
+
+#[test]
+fn binary_round_trip() {
+    let bytes = lex("a + 12\nb").into_binary().unwrap();
+    let tokens = serial::from_binary::<Token>(&bytes).unwrap();
+    assert_eq!(tokens, lex("a + 12\nb").into_vec());
+
+    assert!(serial::from_binary::<Token>(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn locations_and_errors_round_trip() {
+    let loc = SrcLoc::new((1, 2), (3, 4), (5, 6));
+    let json = serde_json::to_string(&loc).unwrap();
+    assert_eq!(serde_json::from_str::<SrcLoc>(&json).unwrap(), loc);
+
+    let err = LexError::Unterminated { terminator: "*/".to_string(), loc };
+    let json = serde_json::to_string(&err).unwrap();
+    assert_eq!(serde_json::from_str::<LexError>(&json).unwrap(), err);
+}
+
+#[test]
+fn rule_reports_serialize() {
+    let rules = serde_json::to_value(lex("").rules()).unwrap();
+    assert_eq!(rules[1]["source"], "\"[a-z]+\"");
+    assert_eq!(rules[1]["index"], 1);
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "stats")]
+use lexr::{lex_rule, Action, Counters, LexerBuilder};
+
+lex_rule!{lex(skip: bool) -> u8 {
+    "x" if skip => |_| 0,
+    ws => |_| continue,
+    "[a-z]+" => |_| 1,
+    "[0-9]+" => |_| 2,
+    r"/\*" until |_| "*/".to_string() => |_| 3,
+    "y" => |_| 4,
+}}
+
+fn counts(counters: &Counters) -> (u64, u64, u64) {
+    (counters.attempts, counters.matches, counters.bytes)
+}
+
+#[test]
+fn rules_are_counted() {
+    let mut lexer = lex("ab 12 /* c */ cd", false);
+    while lexer.next().is_some() {}
+
+    let stats = lexer.stats();
+    let counted = stats.rules.iter().map(|rule| (rule.rule.source.as_str(), counts(&rule.counters))).collect::<Vec<_>>();
+    assert_eq!(counted, vec![
+        ("\"x\"", (0, 0, 0)),
+        ("ws", (8, 3, 3)),
+        ("\"[a-z]+\"", (5, 2, 4)),
+        ("\"[0-9]+\"", (3, 1, 2)),
+        (r#"r"/\*""#, (2, 1, 7)),
+        ("\"y\"", (1, 0, 0)),
+    ]);
+}
+
+#[test]
+fn unused_rules_are_reported() {
+    let mut lexer = lex("ab", true);
+    while lexer.next().is_some() {}
+
+    let stats = lexer.stats();
+    let unused = stats.unused().map(|rule| rule.rule.source.as_str()).collect::<Vec<_>>();
+    assert_eq!(unused, vec!["\"x\"", "ws", "\"[0-9]+\"", r#"r"/\*""#, "\"y\""]);
+}
+
+#[test]
+fn stats_are_printed_as_a_table() {
+    let mut lexer = lex("ab", false);
+    while lexer.next().is_some() {}
+
+    let table = lexer.stats().to_string();
+    let lines = table.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 7);
+    assert!(lines[0].starts_with("rule"));
+    assert!(lines[3].starts_with("\"[a-z]+\""));
+}
+
+#[test]
+fn runtime_lexers_are_counted() {
+    let builder = LexerBuilder::new()
+        .rule("[a-z]+", Action::token(1))
+        .rule("\"", Action::push("string"))
+        .mode("string")
+        .rule("[^\"]+", Action::token(2))
+        .rule("\"", Action::Pop);
+    let runtime = builder.build().unwrap();
+    let mut lexer = runtime.lex("ab\"c\"");
+    while lexer.next().is_some() {}
+
+    // The modes are listed in the order they were added, and the initial mode is tried again at the end of the input
+    let stats = lexer.stats();
+    let counted = stats.rules.iter().map(|rule| (rule.rule.source.as_str(), counts(&rule.counters))).collect::<Vec<_>>();
+    assert_eq!(counted, vec![
+        ("\"[a-z]+\"", (3, 1, 2)),
+        ("\"\\\"\"", (2, 1, 1)),
+        ("\"[^\\\"]+\"", (2, 1, 1)),
+        ("\"\\\"\"", (1, 1, 1)),
+    ]);
+}
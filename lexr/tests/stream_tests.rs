@@ -0,0 +1,147 @@
+#![cfg(feature = "async")]
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures::{executor::block_on, io::AsyncRead, StreamExt};
+use lexr::{lex_rule, Action, LexBuf, LexerBuilder, SrcLoc};
+
+/// A reader that returns one part of the source per read, and is pending between the reads like a socket.
+struct Parts {
+    parts: VecDeque<Vec<u8>>,
+    /// Flipped by each read, which is pending when it is false
+    ready: bool,
+}
+
+impl Parts {
+    fn new<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        Self { parts: parts.into_iter().map(|part| part.to_vec()).collect(), ready: true }
+    }
+
+    fn bytes(src: &str) -> Self {
+        Self::new(src.as_bytes().chunks(1))
+    }
+}
+
+impl AsyncRead for Parts {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending
+        }
+        let Some(part) = self.parts.pop_front() else {
+            return Poll::Ready(Ok(0))
+        };
+        buf[..part.len()].copy_from_slice(&part);
+        Poll::Ready(Ok(part.len()))
+    }
+}
+
+fn collect<T>(stream: impl futures::Stream<Item = io::Result<T>>) -> Vec<T> {
+    block_on(stream.map(Result::unwrap).collect())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Id(String), Int(u32), Str(String), Eq, Assign, If, Comment, Eof,
+}
+use Token::*;
+
+lex_rule!{comment -> () {
+    r"\*/" => |_| break,
+    r"/\*" => |_, buf| { comment(buf).deplete(); continue },
+    r"(?s:.)" => |_| continue,
+}}
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r"/\*" => |_, buf| { comment(buf).deplete(); Comment },
+    "==" => |_| Eq,
+    "=" => |_| Assign,
+    "[0-9]+" => |s| Int(s.parse().unwrap()),
+    "\"" until |_| "\"" => |s| Str(s.to_string()),
+    r"\w+" keywords {
+        "if" => If,
+    } => |s| Id(s.to_string()),
+    eof => |_| Eof,
+}}
+
+const SRC: &str = "if é1 == 42 /* a /* nested */ comment */ = \"a\nb\" 7";
+
+#[test]
+fn tokens_split_between_reads() {
+    let expected = lex(SRC).into_vec();
+    assert_eq!(expected.last(), Some(&(Eof, SrcLoc::new((2, 5), (2, 5), (51, 51)))));
+
+    assert_eq!(collect(lex("").stream(Parts::bytes(SRC))), expected);
+    for split in (1..SRC.len()).filter(|i| SRC.is_char_boundary(*i)) {
+        let (a, b) = SRC.split_at(split);
+        assert_eq!(collect(lex("").stream(Parts::new([a.as_bytes(), b.as_bytes()]))), expected, "split at {}", split);
+    }
+    // A character split between reads
+    let bytes = SRC.as_bytes();
+    assert_eq!(collect(lex("").stream(Parts::new([&bytes[..4], &bytes[4..]]))), expected);
+}
+
+#[test]
+fn lexer_state_is_kept_between_reads() {
+    lex_rule!{after_id(limit: u32) -> Token, prev: bool = is_id {
+        ws => |_| continue,
+        "[0-9]+" if prev == Some(true) => |s| Int(s.parse::<u32>().unwrap().min(limit)),
+        "[0-9]+" => |s| Str(s.to_string()),
+        "[a-z]+" => |s| Id(s.to_string()),
+    }}
+    fn is_id(token: &Token) -> bool {
+        matches!(token, Id(_))
+    }
+
+    let src = "a 12 34 b 56";
+    assert_eq!(collect(after_id("", 40).stream(Parts::bytes(src))), after_id(src, 40).into_vec());
+
+    let lexer = LexerBuilder::new()
+        .builtin("ws", Action::Skip)
+        .rule("[a-z]+", Action::emit(|s, _| Id(s.to_string())))
+        .rule("\"", Action::push("string"))
+        .mode("string")
+        .rule("[^\"]+", Action::emit(|s, _| Str(s.to_string())))
+        .rule("\"", Action::Pop)
+        .build()
+        .unwrap();
+    let src = "say \"hi there\" bye";
+    assert_eq!(collect(lexer.lex("").stream(Parts::bytes(src))), lexer.lex(src).into_vec());
+}
+
+#[test]
+fn streams_end_at_break_and_errors() {
+    lex_rule!{until_dot -> Token {
+        "[a-z]+" => |s| Id(s.to_string()),
+        r"\." => |_| break,
+    }}
+    assert_eq!(collect(until_dot("").stream(Parts::bytes("ab.cd"))), until_dot("ab.cd").into_vec());
+
+    let results = block_on(lex("").stream(Parts::new([b"1 \xff".as_slice()])).collect::<Vec<_>>());
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap(), &(Int(1), SrcLoc::new((1, 1), (1, 1), (0, 1))));
+    assert_eq!(results[1].as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let results = block_on(lex("").stream(Parts::new([b"1 \xc3".as_slice()])).collect::<Vec<_>>());
+    assert_eq!(results[1].as_ref().unwrap_err().to_string(), "The source ends in an incomplete UTF-8 sequence");
+}
+
+#[test]
+#[should_panic(expected = "Unexpected character '%' at 1:3")]
+fn unexpected_characters_panic() {
+    collect(lex("").stream(Parts::bytes("1 % 2")));
+}
+
+#[test]
+fn buffers_wait_for_more_source() {
+    // The lexers decide when more source is needed from the partial flag of the buffer
+    let src = "ab";
+    let buf = LexBuf::from(src);
+    buf.set_partial(true);
+    let shared = buf.share();
+    assert_eq!(lex(buf).into_vec(), vec![]);
+    assert!(shared.is_starved());
+}
@@ -0,0 +1,182 @@
+#![cfg(feature = "std")]
+use lexr::{lex_rule, Action, ExportWarning, LexerBuilder, TextMate};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    If, In, Id, Int, Range, Str,
+}
+use Token::*;
+
+lex_rule!{lex -> Token {
+    ws => |_| continue,
+    r"//[^\n]*" => |_| continue,
+    "[0-9]+" / r"\.\." => |_| Int,
+    #[name(int)]
+    "[0-9]+" => |_| Int,
+    r"\.\." => |_| Range,
+    "[a-z]+" keywords {
+        "if" => If,
+        "in" => In,
+    } => |_| Id,
+    eof => |_| break,
+}}
+
+#[test]
+fn rules_are_exported_in_order() {
+    let textmate = TextMate::new("Calc", "source.calc")
+        .file_type("calc")
+        .scope("int", "constant.numeric")
+        .scope("if", "keyword.control")
+        .scope("in", "keyword.control")
+        .scope(r"//[^\n]*", "comment.line");
+    let grammar = lex("").textmate(&textmate);
+
+    assert_eq!(grammar.warnings, vec![]);
+    assert_eq!(grammar.json, r##"{
+  "name": "Calc",
+  "scopeName": "source.calc",
+  "fileTypes": [
+    "calc"
+  ],
+  "patterns": [
+    {
+      "include": "#INITIAL"
+    }
+  ],
+  "repository": {
+    "INITIAL": {
+      "patterns": [
+        {
+          "match": "[\\s\\p{Pattern_White_Space}]"
+        },
+        {
+          "match": "//[^\\n]*",
+          "name": "comment.line"
+        },
+        {
+          "match": "[0-9]+(?=\\.\\.)"
+        },
+        {
+          "match": "[0-9]+",
+          "name": "constant.numeric"
+        },
+        {
+          "match": "\\.\\."
+        },
+        {
+          "match": "\\b(?:if|in)\\b",
+          "name": "keyword.control"
+        },
+        {
+          "match": "[a-z]+"
+        }
+      ]
+    }
+  }
+}
+"##);
+}
+
+#[test]
+fn pushed_modes_become_regions() {
+    let lexer = LexerBuilder::new()
+        .builtin("ws", Action::Skip)
+        .rule("[a-z]+", Action::token(Id))
+        .rule("\"", Action::push("string"))
+        .mode("string")
+        .rule("\"", Action::Pop)
+        .rule(r#"[^"]+"#, Action::token(Str))
+        .build()
+        .unwrap();
+    let grammar = lexer.textmate(&TextMate::new("Calc", "source.calc")
+        .scope("string", "string.quoted.double")
+        .scope("\"", "punctuation.definition.string"));
+
+    assert_eq!(grammar.warnings, vec![]);
+    assert!(grammar.json.contains(r##"
+        {
+          "begin": "\"",
+          "beginCaptures": {
+            "0": {
+              "name": "punctuation.definition.string"
+            }
+          },
+          "end": "\"",
+          "endCaptures": {
+            "0": {
+              "name": "punctuation.definition.string"
+            }
+          },
+          "name": "string.quoted.double",
+          "patterns": [
+            {
+              "include": "#string"
+            }
+          ]
+        }
+"##));
+    assert!(grammar.json.contains(r#"
+    "string": {
+      "patterns": [
+        {
+          "match": "[^\"]+"
+        }
+      ]
+    }
+"#));
+}
+
+lex_rule!{translated -> Token {
+    ^ "#" => |_| Int,
+    #[case_insensitive]
+    "if" / "[ (]" => |_| If,
+    r"(?P<name>[a-z--q])(?s:.)" => |_| Id,
+    r"(?-u:\b)x" => |_| Id,
+}}
+
+#[test]
+fn patterns_are_translated() {
+    let json = translated("").textmate(&TextMate::new("Calc", "source.calc")).json;
+    for regex in [r#""^#""#, r#""(?i:if)(?=(?i:[ (]))""#, r#""(?<name>[a-z&&[^q]])(?m:.)""#, r#""(?:\\b)x""#] {
+        assert!(json.contains(regex), "{} is not in {}", regex, json);
+    }
+}
+
+#[test]
+fn unrepresentable_constructs_are_warned_about() {
+    lex_rule!{unusual(strict: bool) -> Token {
+        "[a-z]+" if strict => |_| Id,
+        "\"" until |_| "\"".to_string() => |_| Str,
+        r"\<in" => |_| In,
+        "(?U)[0-9]+" => |_| Int,
+    }}
+    let warnings = unusual("", true).textmate(&TextMate::new("Calc", "source.calc")).warnings;
+    assert_eq!(warnings.iter().map(ExportWarning::to_string).collect::<Vec<_>>(), vec![
+        r#""[a-z]+": The guard can not be represented, so the rule is exported as if it always holds"#,
+        r#""\"": Only the opening of a delimited pattern can be represented"#,
+        r#"r"\<in": Only `\b` and `\B` word boundaries are supported by TextMate"#,
+        r#""(?U)[0-9]+": The `U` flag is not supported by TextMate"#,
+    ]);
+
+    let lexer = LexerBuilder::new()
+        .rule("[a-z]+", Action::token(Id))
+        .rule(";", Action::Break)
+        .rule("#", Action::switch("other"))
+        .rule("\"", Action::push("string"))
+        .mode("string")
+        .rule(r"\\.", Action::token(Str))
+        .rule("\"", Action::Pop)
+        .rule(r"[^\\]", Action::token(Str))
+        .rule("\n", Action::Pop)
+        .mode("other")
+        .rule("x", Action::token(Str))
+        .build()
+        .unwrap();
+    let warnings = lexer.textmate(&TextMate::new("Calc", "source.calc").scope("\"", "punctuation.definition.string")).warnings;
+    assert_eq!(warnings.iter().map(ExportWarning::to_string).collect::<Vec<_>>(), vec![
+        "string: The rules popping the mode have different scopes, so the end of its region is not highlighted",
+        "string: The rules popping the mode are tried after all of its other rules",
+        r#"";": Stopping can not be represented, so the rule is exported without it"#,
+        r##""#": Switching to mode other can not be represented, so the rule is exported without it"##,
+    ]);
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "log")]
+use std::sync::Mutex;
+use lexr::lex_rule;
+
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Collector;
+
+impl log::Log for Collector {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "lexr"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            MESSAGES.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn every_rule_attempt_is_traced() {
+    log::set_logger(&Collector).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    lex_rule!{lex(stop: bool) -> u8 {
+        "a" if stop => |_| break,
+        ws => |_| continue,
+        "[a-z]+" keywords { "if" => 1 } => |_| 2,
+    }}
+
+    assert_eq!(lex("if\nab a", false).into_token_vec(), vec![1, 2, 2]);
+    assert_eq!(lex("a", true).into_token_vec(), Vec::<u8>::new());
+
+    let messages = MESSAGES.lock().unwrap();
+    assert_eq!(*messages, [
+        "1:1: skipped \"a\", the guard does not hold",
+        "1:1: tried ws, it did not match",
+        "1:1: selected \"[a-z]+\", it matched \"if\"",
+        "1:1-2: the action of \"[a-z]+\" was replaced by a keyword",
+        "1:3: skipped \"a\", the guard does not hold",
+        "1:3: selected ws, it matched \"\\n\"",
+        "1:3: the action of ws continued",
+        "2:1: skipped \"a\", the guard does not hold",
+        "2:1: tried ws, it did not match",
+        "2:1: selected \"[a-z]+\", it matched \"ab\"",
+        "2:1-2: the action of \"[a-z]+\" produced a token",
+        "2:3: skipped \"a\", the guard does not hold",
+        "2:3: selected ws, it matched \" \"",
+        "2:3: the action of ws continued",
+        "2:4: skipped \"a\", the guard does not hold",
+        "2:4: tried ws, it did not match",
+        "2:4: selected \"[a-z]+\", it matched \"a\"",
+        "2:4: the action of \"[a-z]+\" produced a token",
+        "2:5: skipped \"a\", the guard does not hold",
+        "2:5: tried ws, it did not match",
+        "2:5: tried \"[a-z]+\", it did not match",
+        "2:5: no rule matched",
+        "1:1: selected \"a\", it matched \"a\"",
+        "1:1: the action of \"a\" stopped lexing",
+    ]);
+}